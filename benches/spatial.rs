@@ -0,0 +1,94 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use swarm_it::*;
+
+const SPACING: f64 = 10.0;
+
+// Square layout with `count` slots. About every 100th slot is a pit and every 1000th
+// is a spawner, the rest already hold their target payload.
+fn layout(count: usize) -> Swarm<char> {
+    let side = (count as f64).sqrt().ceil() as usize;
+    let mut game = Swarm::with_seed(2019);
+    for i in 0..count {
+        let x = (i % side) as f64 * SPACING;
+        let y = (i / side) as f64 * SPACING;
+        let slot = if i % 997 == 500 {
+            Slot::new(x, y, None, None, SlotKind::SPAWNER)
+        } else if i % 97 == 50 {
+            Slot::new(x, y, None, None, SlotKind::PIT)
+        } else {
+            Slot::new(
+                x,
+                y,
+                Some(Payload::new('A')),
+                Some(Payload::new('A')),
+                SlotKind::CLASSIC,
+            )
+        };
+        game.add_slot(slot);
+    }
+    game
+}
+
+// Looks up the closest pit and spawner for each probe position and hands out no tasks
+struct Probe {
+    positions: Vec<Position>,
+}
+
+impl DispatchStrategy<char> for Probe {
+    fn assign(&mut self, context: &DispatchContext<char>) -> Vec<Assignment<char>> {
+        for pos in &self.positions {
+            black_box(context.find_closest_slot_of_kind(pos, SlotKind::PIT));
+            black_box(context.find_closest_slot_of_kind(pos, SlotKind::SPAWNER));
+        }
+        Vec::new()
+    }
+}
+
+fn nearest_slot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nearest_slot");
+    for &count in &[1_000, 10_000, 100_000] {
+        let mut game = layout(count);
+        let extent = (count as f64).sqrt() * SPACING;
+        game.set_strategy(Probe {
+            positions: (0..100)
+                .map(|i| Position::new((i * 37) as f64 % extent, (i * 53) as f64 % extent))
+                .collect(),
+        });
+        game.add_carrier(Carrier::new(0.0, 0.0));
+        game.tick().unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter(|| game.tick().unwrap())
+        });
+    }
+    group.finish();
+}
+
+// Single payload in the corner of the layout must be brought to the closest pit
+fn transfer_to_pit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transfer_to_pit");
+    group.sample_size(10);
+    for &count in &[1_000, 10_000, 100_000] {
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            b.iter_batched(
+                || {
+                    let mut game = layout(count);
+                    game.add_slot(Slot::new(
+                        -SPACING,
+                        -SPACING,
+                        Some(Payload::new('X')),
+                        None,
+                        SlotKind::CLASSIC,
+                    ));
+                    game.add_carrier(Carrier::new(0.0, 0.0));
+                    game
+                },
+                |mut game| while !game.tick().unwrap().is_finished() {},
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, nearest_slot, transfer_to_pit);
+criterion_main!(benches);
@@ -0,0 +1,338 @@
+use std::fmt;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+/// Handle to the object stored in the `Arena`. Handles stay valid when
+/// other objects are removed and are recognized as stale once the object
+/// they point to is gone, even if its place was reused.
+pub trait ArenaId: Copy {
+    #[doc(hidden)]
+    fn from_parts(index: usize, generation: u32) -> Self;
+    /// Position of the object inside the arena
+    fn index(&self) -> usize;
+    /// Generation of the place inside the arena, increased with each removal
+    fn generation(&self) -> u32;
+}
+
+macro_rules! define_arena_id {
+    ($(#[$meta:meta])* $name: ident) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $name {
+            index: usize,
+            generation: u32,
+        }
+
+        impl ArenaId for $name {
+            fn from_parts(index: usize, generation: u32) -> Self {
+                $name { index, generation }
+            }
+
+            fn index(&self) -> usize {
+                self.index
+            }
+
+            fn generation(&self) -> u32 {
+                self.generation
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}v{}", self.index, self.generation)
+            }
+        }
+    };
+}
+
+define_arena_id!(
+    /// Stable handle to the `Carrier` added to the `Swarm`
+    CarrierId
+);
+
+define_arena_id!(
+    /// Stable handle to the `Slot` added to the `Swarm`
+    SlotId
+);
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Entry<V> {
+    Occupied {
+        generation: u32,
+        value: V,
+    },
+    Free {
+        generation: u32,
+        next_free: Option<usize>,
+    },
+}
+
+/// Generational arena that stores carriers and slots of the `Swarm`.
+/// Objects are accessed by their handles (`CarrierId` and `SlotId`)
+/// which are never reused for different objects.
+///
+/// Iteration order is the order of places inside the arena, which
+/// is the insertion order as long as nothing is removed.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arena<I: ArenaId, V> {
+    entries: Vec<Entry<V>>,
+    free_head: Option<usize>,
+    len: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _id: PhantomData<I>,
+}
+
+impl<I: ArenaId, V> Default for Arena<I, V> {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+
+impl<I: ArenaId, V> Arena<I, V> {
+    /// Constructs a new, empty `Arena`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let arena = swarm_it::Arena::<swarm_it::SlotId, char>::new();
+    /// assert!(arena.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        Arena {
+            entries: Vec::new(),
+            free_head: None,
+            len: 0,
+            _id: PhantomData,
+        }
+    }
+
+    /// Stores the object and returns its handle
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut arena = swarm_it::Arena::<swarm_it::SlotId, char>::new();
+    /// let id = arena.insert('X');
+    /// assert_eq!(arena[id], 'X');
+    /// ```
+    pub fn insert(&mut self, value: V) -> I {
+        self.len += 1;
+        match self.free_head {
+            Some(index) => {
+                let generation = match self.entries[index] {
+                    Entry::Free {
+                        generation,
+                        next_free,
+                    } => {
+                        self.free_head = next_free;
+                        generation
+                    }
+                    Entry::Occupied { .. } => unreachable!("Free list points to occupied entry"),
+                };
+                self.entries[index] = Entry::Occupied { generation, value };
+                I::from_parts(index, generation)
+            }
+            None => {
+                self.entries.push(Entry::Occupied {
+                    generation: 0,
+                    value,
+                });
+                I::from_parts(self.entries.len() - 1, 0)
+            }
+        }
+    }
+
+    /// Removes the object and returns it. Returns `None` if the handle is stale.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut arena = swarm_it::Arena::<swarm_it::SlotId, char>::new();
+    /// let id = arena.insert('X');
+    /// assert_eq!(arena.remove(id), Some('X'));
+    /// assert_eq!(arena.remove(id), None);
+    /// ```
+    pub fn remove(&mut self, id: I) -> Option<V> {
+        if !self.contains(id) {
+            return None;
+        }
+        let entry = std::mem::replace(
+            &mut self.entries[id.index()],
+            Entry::Free {
+                generation: id.generation().wrapping_add(1),
+                next_free: self.free_head,
+            },
+        );
+        self.free_head = Some(id.index());
+        self.len -= 1;
+        match entry {
+            Entry::Occupied { value, .. } => Some(value),
+            Entry::Free { .. } => None,
+        }
+    }
+
+    /// Returns `true` if the handle points to the existing object
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut arena = swarm_it::Arena::<swarm_it::SlotId, char>::new();
+    /// let id = arena.insert('X');
+    /// assert!(arena.contains(id));
+    /// arena.remove(id);
+    /// assert!(!arena.contains(id));
+    /// ```
+    pub fn contains(&self, id: I) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Returns the object or `None` if the handle is stale
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut arena = swarm_it::Arena::<swarm_it::SlotId, char>::new();
+    /// let id = arena.insert('X');
+    /// assert_eq!(arena.get(id), Some(&'X'));
+    /// ```
+    pub fn get(&self, id: I) -> Option<&V> {
+        match self.entries.get(id.index()) {
+            Some(Entry::Occupied { generation, value }) if *generation == id.generation() => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the mutable object or `None` if the handle is stale
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut arena = swarm_it::Arena::<swarm_it::SlotId, char>::new();
+    /// let id = arena.insert('X');
+    /// *arena.get_mut(id).unwrap() = 'Y';
+    /// assert_eq!(arena[id], 'Y');
+    /// ```
+    pub fn get_mut(&mut self, id: I) -> Option<&mut V> {
+        match self.entries.get_mut(id.index()) {
+            Some(Entry::Occupied { generation, value }) if *generation == id.generation() => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the number of stored objects
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there are no objects stored
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over handles and objects
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut arena = swarm_it::Arena::<swarm_it::SlotId, char>::new();
+    /// let id = arena.insert('X');
+    /// assert_eq!(arena.iter().next(), Some((id, &'X')));
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (I, &V)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| match entry {
+                Entry::Occupied { generation, value } => {
+                    Some((I::from_parts(index, *generation), value))
+                }
+                Entry::Free { .. } => None,
+            })
+    }
+
+    /// Iterates over handles and mutable objects
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (I, &mut V)> {
+        self.entries
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, entry)| match entry {
+                Entry::Occupied { generation, value } => {
+                    Some((I::from_parts(index, *generation), value))
+                }
+                Entry::Free { .. } => None,
+            })
+    }
+
+    /// Iterates over handles
+    pub fn ids(&self) -> impl Iterator<Item = I> + '_ {
+        self.iter().map(|(id, _)| id)
+    }
+
+    /// Iterates over objects
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<I: ArenaId, V> Index<I> for Arena<I, V> {
+    type Output = V;
+
+    fn index(&self, id: I) -> &V {
+        self.get(id).expect("Stale arena handle")
+    }
+}
+
+impl<I: ArenaId, V> IndexMut<I> for Arena<I, V> {
+    fn index_mut(&mut self, id: I) -> &mut V {
+        self.get_mut(id).expect("Stale arena handle")
+    }
+}
+
+impl<I: ArenaId, V> FromIterator<V> for Arena<I, V> {
+    fn from_iter<It: IntoIterator<Item = V>>(iter: It) -> Self {
+        let mut arena = Arena::new();
+        iter.into_iter().for_each(|value| {
+            arena.insert(value);
+        });
+        arena
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::*;
+
+    #[test]
+    fn stale_handle_is_detected_after_reuse() {
+        let mut arena = Arena::<SlotId, char>::new();
+        let a = arena.insert('A');
+        let b = arena.insert('B');
+        arena.remove(a);
+        let c = arena.insert('C');
+
+        // Place of 'A' is reused, but the old handle must not reach 'C'
+        assert_eq!(c.index(), a.index());
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena[c], 'C');
+        assert_eq!(arena[b], 'B');
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn iteration_skips_removed() {
+        let mut arena: Arena<CarrierId, u32> = (0..5).collect();
+        let ids: Vec<CarrierId> = arena.ids().collect();
+        arena.remove(ids[1]);
+        arena.remove(ids[3]);
+
+        assert_eq!(arena.values().cloned().collect::<Vec<_>>(), vec![0, 2, 4]);
+    }
+}
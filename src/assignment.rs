@@ -0,0 +1,113 @@
+// Solver for the rectangular assignment problem (Hungarian algorithm).
+
+/// Finds the assignment of rows to columns with the minimal total cost.
+///
+/// The matrix must have at most as many rows as columns. Returned vector
+/// contains the column assigned to each row. Runs in O(rows² * columns).
+pub(crate) fn solve(costs: &[Vec<f64>]) -> Vec<usize> {
+    let rows = costs.len();
+    if rows == 0 {
+        return Vec::new();
+    }
+    let columns = costs[0].len();
+    debug_assert!(rows <= columns, "More rows than columns");
+
+    // Potentials and matching are kept 1-based, index 0 is a sentinel
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; columns + 1];
+    let mut matched_row = vec![0; columns + 1];
+    let mut way = vec![0; columns + 1];
+
+    for row in 1..=rows {
+        matched_row[0] = row;
+        let mut current_column = 0;
+        let mut min_value = vec![f64::INFINITY; columns + 1];
+        let mut used = vec![false; columns + 1];
+        loop {
+            used[current_column] = true;
+            let current_row = matched_row[current_column];
+            let mut delta = f64::INFINITY;
+            let mut next_column = 0;
+            for column in 1..=columns {
+                if used[column] {
+                    continue;
+                }
+                let reduced = costs[current_row - 1][column - 1] - u[current_row] - v[column];
+                if reduced < min_value[column] {
+                    min_value[column] = reduced;
+                    way[column] = current_column;
+                }
+                if min_value[column] < delta {
+                    delta = min_value[column];
+                    next_column = column;
+                }
+            }
+            for column in 0..=columns {
+                if used[column] {
+                    u[matched_row[column]] += delta;
+                    v[column] -= delta;
+                } else {
+                    min_value[column] -= delta;
+                }
+            }
+            current_column = next_column;
+            if matched_row[current_column] == 0 {
+                break;
+            }
+        }
+        loop {
+            let previous_column = way[current_column];
+            matched_row[current_column] = matched_row[previous_column];
+            current_column = previous_column;
+            if current_column == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut result = vec![0; rows];
+    for column in 1..=columns {
+        if matched_row[column] != 0 {
+            result[matched_row[column] - 1] = column - 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assignment::*;
+
+    #[test]
+    fn solve_square() {
+        let costs = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+
+        // 1 + 2 + 2 = 5 is the optimum
+        assert_eq!(solve(&costs), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn solve_rectangular() {
+        let costs = vec![vec![10.0, 1.0, 7.0, 3.0], vec![9.0, 2.0, 8.0, 100.0]];
+
+        assert_eq!(solve(&costs), vec![3, 1]);
+    }
+
+    #[test]
+    fn solve_greedy_trap() {
+        // Greedy would give column 0 to the first row and make
+        // the second row pay a lot
+        let costs = vec![vec![1.0, 2.0], vec![1.0, 100.0]];
+
+        assert_eq!(solve(&costs), vec![1, 0]);
+    }
+
+    #[test]
+    fn solve_empty() {
+        assert!(solve(&[]).is_empty());
+    }
+}
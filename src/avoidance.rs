@@ -0,0 +1,115 @@
+// Local collision avoidance between carriers that have the radius set.
+//
+// Carriers are ranked: moving carriers with payload go first, then other
+// moving carriers, then the rest. Ties are broken by the handle, so the order
+// is total and carriers can never wait for each other in a cycle.
+// Carrier waits if the higher ranked one is in front of it, lower ranked
+// carrier that is in the way steps aside. Overlapping carriers are pushed apart.
+
+use std::hash::Hash;
+
+use super::arena::*;
+use super::carrier::*;
+use super::position::*;
+
+// Used when two carriers are exactly at the same spot
+const COINCIDENT_DISTANCE: f64 = 1e-9;
+
+struct Body {
+    id: CarrierId,
+    pos: Position,
+    radius: f64,
+    heading: (f64, f64),
+    moving: bool,
+    look_ahead: f64,
+    sidestep: f64,
+    rank: u8,
+}
+
+impl Body {
+    fn new<T: PartialEq + Eq + Hash + Copy>(id: CarrierId, carrier: &Carrier<T>, dt: f64) -> Self {
+        let moving = matches!(carrier.get_state(), State::MOVING(_));
+        Body {
+            id,
+            pos: *carrier.get_position(),
+            radius: carrier.get_radius(),
+            heading: (carrier.get_angle().cos(), carrier.get_angle().sin()),
+            moving,
+            look_ahead: carrier.calculate_distance_to_stop(dt) + carrier.get_speed() * dt,
+            sidestep: carrier.get_max_speed() * dt,
+            rank: match (moving, carrier.is_loaded()) {
+                (true, true) => 2,
+                (true, false) => 1,
+                _ => 0,
+            },
+        }
+    }
+
+    fn outranks(&self, other: &Body) -> bool {
+        self.rank > other.rank || (self.rank == other.rank && self.id < other.id)
+    }
+}
+
+pub(crate) fn avoid_collisions<T: PartialEq + Eq + Hash + Copy>(
+    carriers: &mut Carriers<T>,
+    dt: f64,
+) {
+    let bodies: Vec<Body> = carriers
+        .iter()
+        .filter(|(_, carrier)| carrier.get_radius() > 0.0)
+        .map(|(id, carrier)| Body::new(id, carrier, dt))
+        .collect();
+    let mut yielding = vec![false; bodies.len()];
+    let mut shifts = vec![(0.0, 0.0); bodies.len()];
+
+    for (a, first) in bodies.iter().enumerate() {
+        for (b, second) in bodies.iter().enumerate() {
+            if a == b {
+                continue;
+            }
+            let (dx, dy) = (second.pos.x - first.pos.x, second.pos.y - first.pos.y);
+            let distance = (dx * dx + dy * dy).sqrt();
+            let min_distance = first.radius + second.radius;
+
+            if a < b && distance < min_distance {
+                let (nx, ny) = if distance > COINCIDENT_DISTANCE {
+                    (dx / distance, dy / distance)
+                } else {
+                    (1.0, 0.0)
+                };
+                let push = (min_distance - distance) / 2.0;
+                shifts[a].0 -= nx * push;
+                shifts[a].1 -= ny * push;
+                shifts[b].0 += nx * push;
+                shifts[b].1 += ny * push;
+            }
+
+            if !first.moving {
+                continue;
+            }
+            let ahead = dx * first.heading.0 + dy * first.heading.1;
+            let lateral = dy * first.heading.0 - dx * first.heading.1;
+            if ahead <= 0.0
+                || ahead > min_distance + first.look_ahead
+                || lateral.abs() >= min_distance
+            {
+                continue;
+            }
+            if second.outranks(first) {
+                yielding[a] = true;
+            } else {
+                // Step aside, perpendicular to the path of the first carrier
+                let side = if lateral >= 0.0 { 1.0 } else { -1.0 };
+                let step = (min_distance - lateral.abs()).min(second.sidestep);
+                shifts[b].0 -= first.heading.1 * side * step;
+                shifts[b].1 += first.heading.0 * side * step;
+            }
+        }
+    }
+
+    for (i, body) in bodies.iter().enumerate() {
+        let carrier = &mut carriers[body.id];
+        carrier.yielding = yielding[i];
+        carrier.shift(shifts[i].0, shifts[i].1);
+    }
+}
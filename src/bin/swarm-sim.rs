@@ -0,0 +1,215 @@
+//! Headless simulator. Loads the layout file (see `swarm_it::Layout`), runs the swarm
+//! until the job is finished, the swarm is stalled or the tick limit is reached
+//! and prints the statistics.
+//!
+//! Exit code is 0 if the job was finished, 1 if the swarm stalled or the tick limit
+//! was reached and 2 if the simulation couldn't be run.
+
+use std::process;
+
+use swarm_it::*;
+
+const USAGE: &str = "Usage: swarm-sim <layout.json> [options]
+
+Options:
+    --max-ticks <N>     Stop after N ticks if the job is not finished (default 100000)
+    --seed <N>          Seed of the random number generator (default 0)
+    --strategy <NAME>   Dispatch strategy: greedy (default) or optimal
+    --time-step <DT>    Length of the simulation step in seconds (default 1.0)
+    --json              Print the statistics as JSON";
+
+struct Options {
+    layout: String,
+    max_ticks: u64,
+    seed: u64,
+    optimal: bool,
+    time_step: f64,
+    json: bool,
+}
+
+#[derive(Default)]
+struct Statistics {
+    ticks: u64,
+    finished: bool,
+    stalled: bool,
+    pickups: usize,
+    put_downs: usize,
+    pit_drops: usize,
+    spawns: usize,
+    temporary_drops: usize,
+    reroutes: usize,
+    deadlines_missed: usize,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        layout: String::new(),
+        max_ticks: 100_000,
+        seed: 0,
+        optimal: false,
+        time_step: 1.0,
+        json: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value of {}", name))
+        };
+        match arg.as_str() {
+            "--max-ticks" => {
+                options.max_ticks = value(&arg)?
+                    .parse()
+                    .map_err(|e| format!("invalid --max-ticks: {}", e))?
+            }
+            "--seed" => {
+                options.seed = value(&arg)?
+                    .parse()
+                    .map_err(|e| format!("invalid --seed: {}", e))?
+            }
+            "--strategy" => {
+                options.optimal = match value(&arg)?.as_str() {
+                    "greedy" => false,
+                    "optimal" => true,
+                    other => return Err(format!("unknown strategy: {}", other)),
+                }
+            }
+            "--time-step" => {
+                options.time_step = value(&arg)?
+                    .parse()
+                    .map_err(|e| format!("invalid --time-step: {}", e))?
+            }
+            "--json" => options.json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ if options.layout.is_empty() => options.layout = arg,
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    if options.layout.is_empty() {
+        return Err("layout file is missing".to_string());
+    }
+    if options.time_step <= 0.0 || !options.time_step.is_finite() {
+        return Err("--time-step must be a positive number".to_string());
+    }
+    Ok(options)
+}
+
+fn load(options: &Options) -> Result<Swarm<char>, String> {
+    let layout = Layout::<char>::from_file(&options.layout)
+        .map_err(|e| format!("{}: {}", options.layout, e))?;
+    let mut game = Swarm::with_seed(options.seed);
+    game.add_layout(&layout)
+        .map_err(|e| format!("{}: {}", options.layout, e))?;
+    game.set_time_step(options.time_step);
+    if options.optimal {
+        game.set_strategy(OptimalStrategy);
+    }
+    Ok(game)
+}
+
+fn simulate(game: &mut Swarm<char>, options: &Options) -> Result<Statistics, SwarmError> {
+    let mut statistics = Statistics::default();
+    while statistics.ticks < options.max_ticks && !statistics.finished && !statistics.stalled {
+        let outcome = game.tick_dt(options.time_step)?;
+        statistics.finished = outcome.is_finished();
+        statistics.stalled = outcome.is_stalled();
+        statistics.ticks += 1;
+        for event in game.drain_events() {
+            match event {
+                Event::PickedUp { .. } => statistics.pickups += 1,
+                Event::PutDown { .. } => statistics.put_downs += 1,
+                Event::DroppedIntoPit { .. } => statistics.pit_drops += 1,
+                Event::Spawned { .. } => statistics.spawns += 1,
+                Event::TemporaryDrop { .. } => statistics.temporary_drops += 1,
+                Event::Rerouted { .. } => statistics.reroutes += 1,
+                Event::DeadlineMissed { .. } => statistics.deadlines_missed += 1,
+                Event::StateChanged { .. } | Event::Stalled { .. } => (),
+                Event::Unreachable { .. } => (),
+            }
+        }
+    }
+    Ok(statistics)
+}
+
+fn print_text(game: &Swarm<char>, statistics: &Statistics) {
+    println!("finished: {}", statistics.finished);
+    println!("ticks: {}", statistics.ticks);
+    println!("pickups: {}", statistics.pickups);
+    println!("put downs: {}", statistics.put_downs);
+    println!("pit drops: {}", statistics.pit_drops);
+    println!("spawns: {}", statistics.spawns);
+    println!("temporary drops: {}", statistics.temporary_drops);
+    println!("reroutes: {}", statistics.reroutes);
+    println!("deadlines missed: {}", statistics.deadlines_missed);
+    let mut total = 0.0;
+    for (index, (id, carrier)) in game.get_carriers().iter().enumerate() {
+        println!(
+            "carrier {} ({}): {:.2}",
+            index,
+            id,
+            carrier.get_distance_travelled()
+        );
+        total += carrier.get_distance_travelled();
+    }
+    println!("total distance: {:.2}", total);
+    if let JobStatus::Stalled { reason } = game.progress().get_status() {
+        println!("stalled: {}", reason);
+    }
+}
+
+fn print_json(game: &Swarm<char>, statistics: &Statistics) {
+    let distances: Vec<f64> = game
+        .get_carriers()
+        .values()
+        .map(|carrier| carrier.get_distance_travelled())
+        .collect();
+    let report = serde_json::json!({
+        "finished": statistics.finished,
+        "ticks": statistics.ticks,
+        "pickups": statistics.pickups,
+        "put_downs": statistics.put_downs,
+        "pit_drops": statistics.pit_drops,
+        "spawns": statistics.spawns,
+        "temporary_drops": statistics.temporary_drops,
+        "reroutes": statistics.reroutes,
+        "deadlines_missed": statistics.deadlines_missed,
+        "total_distance": distances.iter().sum::<f64>(),
+        "carrier_distances": distances,
+        "stalled": match game.progress().get_status() {
+            JobStatus::Stalled { reason } => Some(reason.to_string()),
+            _ => None,
+        },
+    });
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|e| {
+        eprintln!("swarm-sim: {}\n\n{}", e, USAGE);
+        process::exit(2);
+    });
+    let mut game = load(&options).unwrap_or_else(|e| {
+        eprintln!("swarm-sim: {}", e);
+        process::exit(2);
+    });
+    if let Err(errors) = game.validate() {
+        for error in errors {
+            eprintln!("swarm-sim: warning: {}", error);
+        }
+    }
+    let statistics = simulate(&mut game, &options).unwrap_or_else(|e| {
+        eprintln!("swarm-sim: simulation failed: {}", e);
+        process::exit(2);
+    });
+
+    if options.json {
+        print_json(&game, &statistics);
+    } else {
+        print_text(&game, &statistics);
+    }
+    process::exit(if statistics.finished { 0 } else { 1 });
+}
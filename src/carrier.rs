@@ -1,6 +1,9 @@
 #![macro_use]
 extern crate rand;
 
+use super::arena::*;
+use super::error::*;
+use super::event::*;
 use super::payload::*;
 use super::position::*;
 use super::slot::*;
@@ -8,9 +11,12 @@ use super::tools::*;
 use rand::Rng;
 use std::hash::Hash;
 
-const ANGLE_INCREMENT: f64 = 0.15;
+// Quantities are per second, `Swarm::tick()` advances the time by one second
+const DEFAULT_TURN_RATE: f64 = 0.15;
 const DEFAULT_ACCELERATION: f64 = 0.47;
 const DEFAULT_MAX_SPEED: f64 = 6.0;
+// Carrier that turns while moving switches to the next waypoint when it is this close
+const WAYPOINT_TOLERANCE: f64 = 1.0;
 
 /// States that apply to Carriers
 ///
@@ -25,51 +31,88 @@ const DEFAULT_MAX_SPEED: f64 = 6.0;
 /// DELIVERING       | Moving payload to the target
 /// PUTTINGDOWN      | Putting down the payload
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum State {
     IDLE,
-    TARGETING(usize),
-    MOVING(usize),
-    PICKINGUP(usize),
+    TARGETING(SlotId),
+    MOVING(SlotId),
+    PICKINGUP(SlotId),
     LOOKINGFORTARGET,
     NOTARGET,
-    DELIVERING(usize),
-    PUTTINGDOWN(usize),
+    DELIVERING(SlotId),
+    PUTTINGDOWN(SlotId),
     _DEBUG_,
 }
 
 impl State {
     pub(crate) fn is_idle(&self) -> bool {
+        matches!(*self, State::IDLE)
+    }
+
+    pub(crate) fn slot(&self) -> Option<SlotId> {
         match *self {
-            State::IDLE => true,
-            _ => false,
+            State::TARGETING(slot)
+            | State::MOVING(slot)
+            | State::PICKINGUP(slot)
+            | State::DELIVERING(slot)
+            | State::PUTTINGDOWN(slot) => Some(slot),
+            _ => None,
         }
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum RotationDirection {
     CLOCKWISE,
     COUNTERCLOCKWISE,
 }
 
+pub(crate) type Carriers<T> = Arena<CarrierId, Carrier<T>>;
+
+// Payload that is carried along, waiting for its turn to be delivered
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Load<T: PartialEq> {
+    pub(crate) payload: Payload<T>,
+    pub(crate) target: Option<SlotId>,
+    pub(crate) to_pit: bool,
+}
+
 /// Represnets the `Carrier` object. Carrier is an entity that moves from slot to slot and
 /// transfers payload in order to reach the desired layout.
-#[derive(Copy, Clone)]
+///
+/// Since the carrier keeps its path around the obstacles, `Carrier` is `Clone`, but no longer `Copy`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Carrier<T: PartialEq + Eq + Hash + Copy> {
     pos: Position,
     angle: f64,
     acceleration: f64,
     effective_acceleration: f64,
+    deceleration: Option<f64>,
     max_speed: f64,
+    turn_rate: f64,
+    turn_while_moving: bool,
+    radius: f64,
+    pub(crate) yielding: bool,
+    pub(crate) path: Vec<Position>,
+    pub(crate) path_target: Option<SlotId>,
     speed: f64,
+    distance_travelled: f64,
     pub(crate) state: State,
     pub(crate) payload: Option<Payload<T>>,
-    pub(crate) reserved_target: Option<usize>,
+    capacity: usize,
+    pub(crate) hold: Vec<Load<T>>,
+    pub(crate) reserved_target: Option<SlotId>,
     rotation_direction: Option<RotationDirection>,
-    idle_rotation_direction: Option<RotationDirection>,
+    pub(crate) idle_rotation_direction: Option<RotationDirection>,
     pub(crate) temporary_target: bool,
     pub(crate) carrying_to_pit: bool,
     pub(crate) going_to_spawner: (bool, Option<T>),
+    // Cargo to pick up, matters for bins that hold different kinds of cargo
+    pub(crate) pickup_cargo: Option<T>,
 }
 
 impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
@@ -86,20 +129,33 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
             angle: 0.0,
             acceleration: DEFAULT_ACCELERATION,
             effective_acceleration: 0.0,
+            deceleration: None,
             max_speed: DEFAULT_MAX_SPEED,
+            turn_rate: DEFAULT_TURN_RATE,
+            turn_while_moving: false,
+            radius: 0.0,
+            yielding: false,
+            path: Vec::new(),
+            path_target: None,
             speed: 0.0,
+            distance_travelled: 0.0,
             state: State::IDLE,
             payload: None,
+            capacity: 1,
+            hold: Vec::new(),
             reserved_target: None,
             rotation_direction: None,
-            idle_rotation_direction: Carrier::<T>::pick_random_idle_rotation(),
+            // Randomized by the swarm when the carrier is added
+            idle_rotation_direction: Some(RotationDirection::CLOCKWISE),
             temporary_target: false,
             carrying_to_pit: false,
             going_to_spawner: (false, None),
+            pickup_cargo: None,
         }
     }
 
-    /// Returns current payload of the carrier
+    /// Returns the payload that the carrier is currently handling. Carriers with
+    /// the capacity greater than one may carry more, see `get_payloads()`.
     ///
     /// # Example
     ///
@@ -113,7 +169,87 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         self.payload
     }
 
-    /// Returns index of the slot that carriers is going to
+    /// Returns all payloads that the carrier carries, the one currently handled goes first
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// assert!(carrier.get_payloads().is_empty());
+    /// ```
+    pub fn get_payloads(&self) -> Vec<Payload<T>> {
+        self.payload
+            .into_iter()
+            .chain(self.hold.iter().map(|load| load.payload))
+            .collect()
+    }
+
+    /// Sets how many payloads the carrier can carry at once. Carrier with the capacity
+    /// greater than one picks up several payloads along its route before delivering
+    /// them, the closest target first. Default capacity is one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// carrier.set_capacity(4);
+    /// assert_eq!(carrier.get_capacity(), 4);
+    /// ```
+    pub fn set_capacity(&mut self, capacity: usize) {
+        assert!(capacity > 0, "Carrier must be able to carry something");
+        self.capacity = capacity;
+    }
+
+    /// Returns how many payloads the carrier can carry at once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// assert_eq!(carrier.get_capacity(), 1);
+    /// ```
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn is_loaded(&self) -> bool {
+        self.payload.is_some() || !self.hold.is_empty()
+    }
+
+    // Is there room for one more payload besides the one in hand
+    pub(crate) fn has_room(&self) -> bool {
+        self.hold.len() + 1 < self.capacity
+    }
+
+    // Puts the payload in hand away together with its destination
+    pub(crate) fn stash_payload(&mut self) {
+        if let Some(payload) = self.payload.take() {
+            self.hold.push(Load {
+                payload,
+                target: self.reserved_target.take(),
+                to_pit: self.carrying_to_pit,
+            });
+        }
+        self.carrying_to_pit = false;
+        self.temporary_target = false;
+        self.going_to_spawner = (false, None);
+    }
+
+    // Takes the payload from the hold, it becomes the one to be delivered next
+    pub(crate) fn unstash_payload(&mut self, index: usize) {
+        let load = self.hold.remove(index);
+        self.payload = Some(load.payload);
+        self.reserved_target = load.target;
+        self.carrying_to_pit = load.to_pit;
+        self.temporary_target = false;
+        self.going_to_spawner = (false, None);
+    }
+
+    /// Returns handle of the slot that carriers is going to
     ///
     /// # Example
     ///
@@ -122,7 +258,7 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
     /// let target = carrier.get_target();
     /// assert_eq!(target, None)
     /// ```
-    pub fn get_target(&self) -> Option<usize> {
+    pub fn get_target(&self) -> Option<SlotId> {
         match self.state {
             State::TARGETING(target_index) => Some(target_index),
             State::MOVING(target_index) => Some(target_index),
@@ -130,7 +266,7 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         }
     }
 
-    /// Returns index of the slot that is reserverd as a
+    /// Returns handle of the slot that is reserverd as a
     /// target for the cargo that the carrier is going to pick-up
     /// or is currently transferring.
     ///
@@ -141,7 +277,7 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
     /// let target = carrier.get_reserved_target();
     /// assert_eq!(target, None)
     /// ```
-    pub fn get_reserved_target(&self) -> Option<usize> {
+    pub fn get_reserved_target(&self) -> Option<SlotId> {
         self.reserved_target
     }
 
@@ -192,10 +328,14 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         self.state
     }
 
-    /// Sets carrier acceleration. This *doesn't* affect the current movement
+    /// Sets carrier acceleration in units per second². This *doesn't* affect the current movement
     /// of a carrier, since this is controlled by the engine. It sets the
     /// carrier's ability to accelerate.
     ///
+    /// # Panics
+    ///
+    /// Panics if the `acceleration` is not positive.
+    ///
     /// # Example
     ///
     /// ```
@@ -204,6 +344,7 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
     /// approx::assert_abs_diff_eq!(carrier.get_acceleration(), 2.71);
     /// ```
     pub fn set_acceleration(&mut self, acceleration: f64) {
+        assert!(acceleration > 0.0, "Acceleration must be positive");
         self.acceleration = acceleration;
     }
 
@@ -222,7 +363,7 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         self.acceleration
     }
 
-    /// Sets maximum speed of a carrier.
+    /// Sets maximum speed of a carrier in units per second.
     ///
     /// # Example
     ///
@@ -248,8 +389,141 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         self.max_speed
     }
 
-    fn pick_random_idle_rotation() -> Option<RotationDirection> {
-        let mut rng = rand::thread_rng();
+    /// Sets carrier deceleration in units per second², used when the
+    /// carrier is braking in front of the target. By default it is the same as acceleration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `deceleration` is not positive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// carrier.set_deceleration(1.5);
+    /// approx::assert_abs_diff_eq!(carrier.get_deceleration(), 1.5);
+    /// ```
+    pub fn set_deceleration(&mut self, deceleration: f64) {
+        assert!(deceleration > 0.0, "Deceleration must be positive");
+        self.deceleration = Some(deceleration);
+    }
+
+    /// Returns carrier deceleration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// carrier.set_acceleration(2.71);
+    /// approx::assert_abs_diff_eq!(carrier.get_deceleration(), 2.71);
+    /// ```
+    pub fn get_deceleration(&self) -> f64 {
+        self.deceleration.unwrap_or(self.acceleration)
+    }
+
+    /// Sets how fast the carrier turns, in radians per second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `turn_rate` is not positive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// carrier.set_turn_rate(0.5);
+    /// approx::assert_abs_diff_eq!(carrier.get_turn_rate(), 0.5);
+    /// ```
+    pub fn set_turn_rate(&mut self, turn_rate: f64) {
+        assert!(turn_rate > 0.0, "Turn rate must be positive");
+        self.turn_rate = turn_rate;
+    }
+
+    /// Returns how fast the carrier turns, in radians per second.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// carrier.set_turn_rate(0.5);
+    /// approx::assert_abs_diff_eq!(carrier.get_turn_rate(), 0.5);
+    /// ```
+    pub fn get_turn_rate(&self) -> f64 {
+        self.turn_rate
+    }
+
+    /// Sets the radius of the carrier. Carriers with the radius greater than zero avoid
+    /// collisions with each other: they wait for the more important carriers
+    /// (the ones delivering payload go first), step aside to let them pass and keep distance
+    /// from one another. By default the radius is zero and carrier drives through others.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// carrier.set_radius(12.0);
+    /// approx::assert_abs_diff_eq!(carrier.get_radius(), 12.0);
+    /// ```
+    pub fn set_radius(&mut self, radius: f64) {
+        self.radius = radius;
+    }
+
+    /// Returns the radius of the carrier.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// approx::assert_abs_diff_eq!(carrier.get_radius(), 0.0);
+    /// ```
+    pub fn get_radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Sets the way the carrier heads to the target.
+    ///
+    /// Value | Behavior
+    /// ------|--------
+    /// false | Carrier stops, rotates towards the target and then drives straight (default)
+    /// true  | Carrier starts driving immediately and follows an arc, slowing down if the turn is too sharp
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// carrier.set_turn_while_moving(true);
+    /// assert!(carrier.get_turn_while_moving());
+    /// ```
+    pub fn set_turn_while_moving(&mut self, turn_while_moving: bool) {
+        self.turn_while_moving = turn_while_moving;
+    }
+
+    /// Returns `true` if the carrier follows arcs instead of rotating in place.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// assert!(!carrier.get_turn_while_moving());
+    /// ```
+    pub fn get_turn_while_moving(&self) -> bool {
+        self.turn_while_moving
+    }
+
+    /// Returns the total distance the carrier has travelled since it was created,
+    /// including the moves made to avoid other carriers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let carrier = swarm_it::Carrier::<char>::new(100.0, 100.0);
+    /// approx::assert_abs_diff_eq!(carrier.get_distance_travelled(), 0.0);
+    /// ```
+    pub fn get_distance_travelled(&self) -> f64 {
+        self.distance_travelled
+    }
+
+    pub(crate) fn pick_random_idle_rotation(rng: &mut SwarmRng) -> Option<RotationDirection> {
         match rng.gen_range(0, 2) {
             0 => Some(RotationDirection::CLOCKWISE),
             1 => Some(RotationDirection::COUNTERCLOCKWISE),
@@ -259,17 +533,23 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
 
     pub(crate) fn target_slot(
         &mut self,
-        target: usize,
+        index: CarrierId,
+        target: SlotId,
         slot: &mut Slot<T>,
         is_temporary: bool,
         to_pit: bool,
         to_spawner: (bool, Option<T>),
-    ) {
+    ) -> Result<(), SwarmError> {
         if slot.is_pit() && self.get_payload().is_none() {
-            panic!("Going empty to the pit, will try to pickup");
+            return Err(SwarmError::EmptyCarrierToPit {
+                carrier: index,
+                slot: target,
+            });
         }
 
         self.state = State::TARGETING(target);
+        self.path.clear();
+        self.path_target = None;
         self.speed = 0.0;
         self.effective_acceleration = self.acceleration;
         slot.taken_care_of = true;
@@ -277,6 +557,8 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         self.temporary_target = is_temporary;
         self.carrying_to_pit = to_pit;
         self.going_to_spawner = to_spawner;
+        self.pickup_cargo = None;
+        Ok(())
     }
 
     fn calculate_angle_to_point(&self, target: (f64, f64)) -> f64 {
@@ -287,39 +569,39 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         angle
     }
 
-    fn rotate(&mut self) {
+    fn rotate(&mut self, dt: f64) {
         if let Some(direction) = self.rotation_direction {
             match direction {
-                RotationDirection::CLOCKWISE => self.turn_clockwise(),
-                RotationDirection::COUNTERCLOCKWISE => self.turn_counterclockwise(),
+                RotationDirection::CLOCKWISE => self.turn_clockwise(dt),
+                RotationDirection::COUNTERCLOCKWISE => self.turn_counterclockwise(dt),
             }
         }
     }
 
-    fn idle_rotate(&mut self) {
+    fn idle_rotate(&mut self, dt: f64) {
         if let Some(direction) = self.idle_rotation_direction {
             match direction {
-                RotationDirection::CLOCKWISE => self.turn_clockwise(),
-                RotationDirection::COUNTERCLOCKWISE => self.turn_counterclockwise(),
+                RotationDirection::CLOCKWISE => self.turn_clockwise(dt),
+                RotationDirection::COUNTERCLOCKWISE => self.turn_counterclockwise(dt),
             }
         }
     }
 
-    fn turn_clockwise(&mut self) {
-        self.angle += ANGLE_INCREMENT;
+    fn turn_clockwise(&mut self, dt: f64) {
+        self.angle += self.turn_rate * dt;
         if self.angle > 2.0 * std::f64::consts::PI {
             self.angle -= 2.0 * std::f64::consts::PI;
         }
     }
 
-    fn turn_counterclockwise(&mut self) {
-        self.angle -= ANGLE_INCREMENT;
+    fn turn_counterclockwise(&mut self, dt: f64) {
+        self.angle -= self.turn_rate * dt;
         if self.angle < 0.0 {
             self.angle += 2.0 * std::f64::consts::PI;
         }
     }
 
-    fn rotate_to(&mut self, target_angle: f64) {
+    fn rotate_to(&mut self, target_angle: f64, dt: f64) {
         if self.rotation_direction.is_none() {
             let src = self.angle;
             let mut trg = target_angle;
@@ -333,7 +615,7 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
             };
         }
 
-        self.rotate();
+        self.rotate(dt);
         if let Some(direction) = self.rotation_direction {
             match direction {
                 RotationDirection::CLOCKWISE => {
@@ -350,10 +632,11 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         }
     }
 
-    fn accelerate(&mut self) -> bool {
-        self.speed += self.effective_acceleration;
-        if self.speed > self.max_speed {
-            self.speed = self.max_speed
+    fn accelerate(&mut self, dt: f64, speed_limit: f64) -> bool {
+        self.speed += self.effective_acceleration * dt;
+        let max_speed = self.max_speed.min(speed_limit);
+        if self.speed > max_speed {
+            self.speed = max_speed
         };
         if self.speed < 0.0 {
             self.speed = 0.0;
@@ -362,121 +645,277 @@ impl<T: PartialEq + Eq + Hash + Copy> Carrier<T> {
         false
     }
 
-    fn calculate_tics_to_decelerate(&self) -> u32 {
-        let mut ticks_to_decelerate = 0;
-        let mut speed_tmp = self.speed;
-        loop {
-            speed_tmp -= self.acceleration;
-            ticks_to_decelerate += 1;
-            if speed_tmp < 0.0 {
-                return ticks_to_decelerate;
-            }
-        }
+    // Number of steps after which the speed drops below zero
+    fn calculate_tics_to_decelerate(&self, dt: f64) -> u32 {
+        (self.speed / self.get_deceleration() / dt).floor() as u32 + 1
     }
 
-    fn calculate_distance_to_stop(&self) -> f64 {
-        let mut distance_to_stop = 0.0;
-        let mut speed_tmp = self.speed;
-        for _ in 0..self.calculate_tics_to_decelerate() {
-            distance_to_stop += speed_tmp;
-            speed_tmp -= self.acceleration;
-        }
-        distance_to_stop
+    // Speed drops by the same amount at each step, so the distance is the sum of an arithmetic series
+    pub(crate) fn calculate_distance_to_stop(&self, dt: f64) -> f64 {
+        let steps = f64::from(self.calculate_tics_to_decelerate(dt));
+        steps * self.speed * dt - self.get_deceleration() * dt * dt * steps * (steps - 1.0) / 2.0
     }
 
-    fn move_forward(&mut self, target: (f64, f64)) -> bool {
+    fn move_forward(&mut self, distance_to_target: f64, dt: f64, speed_limit: f64) -> bool {
         if self.effective_acceleration > 0.0 {
-            let distance_to_stop = self.calculate_distance_to_stop();
-            let distance_to_target =
-                distance_between_positions(&Position::new(target.0, target.1), self.get_position());
+            let distance_to_stop = self.calculate_distance_to_stop(dt);
             if distance_to_stop > distance_to_target {
-                self.effective_acceleration = -self.effective_acceleration;
+                self.effective_acceleration = -self.get_deceleration();
             }
         }
 
-        let at_destination = self.accelerate();
-        self.pos.x += self.angle.cos() * self.speed;
-        self.pos.y += self.angle.sin() * self.speed;
+        let at_destination = self.accelerate(dt, speed_limit);
+        self.advance(dt);
         at_destination
     }
 
-    fn move_forward_to_point(&mut self, target: (f64, f64)) -> bool {
-        self.move_forward(target)
+    fn move_forward_to_point(
+        &mut self,
+        target: (f64, f64),
+        distance_to_stop_point: f64,
+        dt: f64,
+    ) -> bool {
+        let mut speed_limit = f64::INFINITY;
+        if self.turn_while_moving {
+            let target_angle = self.calculate_angle_to_point(target);
+            self.steer_to(target_angle, dt);
+            speed_limit = self.arc_speed_limit(target, target_angle);
+        } else if self.radius > 0.0 {
+            // Carrier might have been pushed aside by others
+            self.steer_to(self.calculate_angle_to_point(target), dt);
+        }
+        self.move_forward(distance_to_stop_point, dt, speed_limit)
+    }
+
+    // Returns the point the carrier is heading to: either the next waypoint or the target itself
+    fn aim_point(&self, target: &Position) -> Position {
+        *self.path.first().unwrap_or(target)
+    }
+
+    // Carrier stops at each waypoint, unless it turns while moving
+    fn distance_to_stop_point(&self, target: &Position) -> f64 {
+        let aim = self.aim_point(target);
+        let mut distance = distance_between_positions(&self.pos, &aim);
+        if self.turn_while_moving {
+            let mut previous = aim;
+            for point in self.path.iter().skip(1).chain(std::iter::once(target)) {
+                distance += distance_between_positions(&previous, point);
+                previous = *point;
+            }
+        }
+        distance
+    }
+
+    // Slows down to let another carrier pass. This is not an arrival, so
+    // the carrier accelerates again once the way is free.
+    fn give_way(&mut self, dt: f64) {
+        self.speed = (self.speed - self.get_deceleration() * dt).max(0.0);
+        self.effective_acceleration = self.acceleration;
+        self.advance(dt);
+    }
+
+    fn advance(&mut self, dt: f64) {
+        self.shift(
+            self.angle.cos() * self.speed * dt,
+            self.angle.sin() * self.speed * dt,
+        );
+    }
+
+    pub(crate) fn shift(&mut self, dx: f64, dy: f64) {
+        self.pos.x += dx;
+        self.pos.y += dy;
+        self.distance_travelled += (dx * dx + dy * dy).sqrt();
+    }
+
+    pub(crate) fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    fn steer_to(&mut self, target_angle: f64, dt: f64) {
+        let difference = angle_difference(target_angle, self.angle);
+        let max_turn = self.turn_rate * dt;
+        if difference.abs() <= max_turn {
+            self.angle = target_angle;
+        } else {
+            self.angle += max_turn.copysign(difference);
+        }
+        if self.angle < 0.0 {
+            self.angle += std::f64::consts::PI * 2.0;
+        } else if self.angle >= std::f64::consts::PI * 2.0 {
+            self.angle -= std::f64::consts::PI * 2.0;
+        }
     }
 
-    pub(crate) fn tick(&mut self, slots: &mut Vec<Slot<T>>) {
+    // Carrier can reach the target along an arc only if its turning radius
+    // (speed / turn rate) is not larger than the radius of the arc that leads
+    // to the target. Targets behind the carrier are faced by turning in place.
+    fn arc_speed_limit(&self, target: (f64, f64), target_angle: f64) -> f64 {
+        let error = angle_difference(target_angle, self.angle).abs();
+        if error > std::f64::consts::FRAC_PI_2 {
+            return 0.0;
+        }
+        let sin = error.sin();
+        if sin < f64::EPSILON {
+            return f64::INFINITY;
+        }
+        let distance =
+            distance_between_positions(&Position::new(target.0, target.1), self.get_position());
+        self.turn_rate * distance / (2.0 * sin)
+    }
+
+    pub(crate) fn tick(
+        &mut self,
+        index: CarrierId,
+        slots: &mut Slots<T>,
+        events: &mut Vec<Event<T>>,
+        rng: &mut SwarmRng,
+        dt: f64,
+    ) -> Result<(), SwarmError> {
         match self.state {
+            State::TARGETING(target) if self.turn_while_moving => {
+                self.state = State::MOVING(target);
+            }
             State::TARGETING(target) => {
-                let target_pos = slots[target].get_position();
+                let target_pos = self.aim_point(slot_at(slots, index, target)?.get_position());
                 let target_angle = self.calculate_angle_to_point((target_pos.x, target_pos.y));
 
-                if !relative_eq!(target_angle, self.angle, epsilon = ANGLE_INCREMENT * 1.2) {
-                    self.rotate_to(target_angle)
+                if !relative_eq!(
+                    target_angle,
+                    self.angle,
+                    epsilon = self.turn_rate * dt * 1.2
+                ) {
+                    self.rotate_to(target_angle, dt)
                 } else {
                     self.angle = target_angle;
                     self.state = State::MOVING(target);
                 }
             }
+            State::MOVING(_) if self.yielding => self.give_way(dt),
             State::MOVING(target) => {
-                let target_pos = slots[target].get_position();
-                if self.move_forward_to_point((target_pos.x, target_pos.y)) {
+                let target_pos = *slot_at(slots, index, target)?.get_position();
+                let aim = self.aim_point(&target_pos);
+                let distance_to_stop_point = self.distance_to_stop_point(&target_pos);
+                if self.move_forward_to_point((aim.x, aim.y), distance_to_stop_point, dt) {
                     self.rotation_direction = None;
                     self.effective_acceleration = self.acceleration;
-                    match self.payload {
-                        Some(_) => self.state = State::PUTTINGDOWN(target),
-                        None => self.state = State::PICKINGUP(target),
+                    if self.path.is_empty() {
+                        match self.payload {
+                            Some(_) => self.state = State::PUTTINGDOWN(target),
+                            None => self.state = State::PICKINGUP(target),
+                        }
+                    } else {
+                        // Waypoint reached, turn towards the next one
+                        self.path.remove(0);
+                        self.state = State::TARGETING(target);
                     }
+                } else if self.turn_while_moving
+                    && !self.path.is_empty()
+                    && distance_between_positions(&self.pos, &aim)
+                        <= self.speed * dt + WAYPOINT_TOLERANCE
+                {
+                    self.path.remove(0);
                 }
             }
             State::PICKINGUP(target) => {
-                if slots[target].is_pit() {
-                    panic!("Trying to pick up from the pit");
+                let slot = slot_at(slots, index, target)?;
+                if slot.is_pit() {
+                    return Err(SwarmError::PickupFromPit {
+                        carrier: index,
+                        slot: target,
+                    });
                 }
 
-                self.payload = if self.going_to_spawner.0 {
-                    Some(Payload {
-                        cargo: self.going_to_spawner.1.unwrap(),
-                        taken_from: None,
-                    })
+                let cargo = if self.going_to_spawner.0 {
+                    self.going_to_spawner.1
                 } else {
-                    slots[target].current_payload
+                    slot.take(self.pickup_cargo).map(|p| p.cargo)
                 };
 
-                if self.payload.is_some() {
-                    self.payload = Some(Payload {
-                        taken_from: Some(target),
-                        cargo: if self.going_to_spawner.0 {
-                            self.going_to_spawner.1.unwrap()
+                match cargo {
+                    Some(cargo) => {
+                        events.push(if self.going_to_spawner.0 {
+                            Event::Spawned {
+                                carrier: index,
+                                slot: target,
+                                cargo,
+                            }
                         } else {
-                            slots[target].current_payload.unwrap().cargo
-                        },
-                    });
-                    slots[target].current_payload = None;
-                    slots[target].taken_care_of = false;
-                    self.state = State::LOOKINGFORTARGET;
-                } else {
-                    panic!("Want to pick up from slot without payload")
+                            Event::PickedUp {
+                                carrier: index,
+                                slot: target,
+                                cargo,
+                            }
+                        });
+                        self.payload = Some(Payload {
+                            taken_from: Some(target),
+                            cargo,
+                        });
+                        slot.taken_care_of = false;
+                        self.state = State::LOOKINGFORTARGET;
+                    }
+                    None => {
+                        return Err(SwarmError::PickupFromEmpty {
+                            carrier: index,
+                            slot: target,
+                        })
+                    }
                 }
             }
             State::PUTTINGDOWN(target) => {
-                if slots[target].is_spawner() {
-                    panic!("Trying to drop into the spawner");
+                let slot = slot_at(slots, index, target)?;
+                if slot.is_spawner() {
+                    return Err(SwarmError::DropIntoSpawner {
+                        carrier: index,
+                        slot: target,
+                    });
                 }
                 if !self.carrying_to_pit {
-                    slots[target].current_payload = self.payload;
-                    slots[target].taken_care_of = false;
+                    if let Some(payload) = self.payload {
+                        slot.put(payload);
+                    }
+                    slot.taken_care_of = false;
+                }
+                if let Some(payload) = self.payload {
+                    let (carrier, slot, cargo) = (index, target, payload.cargo);
+                    events.push(if self.carrying_to_pit {
+                        Event::DroppedIntoPit {
+                            carrier,
+                            slot,
+                            cargo,
+                        }
+                    } else if self.temporary_target {
+                        Event::TemporaryDrop {
+                            carrier,
+                            slot,
+                            cargo,
+                        }
+                    } else {
+                        Event::PutDown {
+                            carrier,
+                            slot,
+                            cargo,
+                        }
+                    });
                 }
                 self.reserved_target = None;
                 self.payload = None;
-                self.state = State::IDLE;
-                self.idle_rotation_direction = Carrier::<T>::pick_random_idle_rotation();
+                if self.hold.is_empty() {
+                    self.state = State::IDLE;
+                    self.idle_rotation_direction = Carrier::<T>::pick_random_idle_rotation(rng);
+                } else {
+                    // Dispatcher decides which one goes next
+                    self.unstash_payload(0);
+                    self.state = State::LOOKINGFORTARGET;
+                }
             }
             State::IDLE | State::NOTARGET => {
-                self.move_forward((5.0, 5.0)); // TODO: Allow moving forward without specifying target
-                self.idle_rotate();
+                let distance = distance_between_positions(&Position::new(5.0, 5.0), &self.pos);
+                self.move_forward(distance, dt, f64::INFINITY); // TODO: Allow moving forward without specifying target
+                self.idle_rotate(dt);
             }
             _ => {}
         }
+        Ok(())
     }
 }
 
@@ -487,7 +926,7 @@ mod tests {
     fn rotate_direction_calculation1() {
         let mut carrier = Carrier::<usize>::new(0.0, 0.0);
         carrier.angle = 0.0;
-        carrier.rotate_to(std::f64::consts::PI / 2.0);
+        carrier.rotate_to(std::f64::consts::PI / 2.0, 1.0);
 
         assert_eq!(
             carrier.rotation_direction.unwrap(),
@@ -499,7 +938,7 @@ mod tests {
     fn rotate_direction_calculation2() {
         let mut carrier = Carrier::<usize>::new(0.0, 0.0);
         carrier.angle = 0.0;
-        carrier.rotate_to(std::f64::consts::PI / 2.0 * 3.0);
+        carrier.rotate_to(std::f64::consts::PI / 2.0 * 3.0, 1.0);
 
         assert_eq!(
             carrier.rotation_direction.unwrap(),
@@ -511,10 +950,69 @@ mod tests {
     fn rotate_direction_calculation3() {
         let mut carrier = Carrier::<usize>::new(0.0, 0.0);
         carrier.angle = 0.0;
-        carrier.rotate_to(std::f64::consts::PI);
+        carrier.rotate_to(std::f64::consts::PI, 1.0);
 
         // When rotating 180deg, choose either left or right direction
         assert!(carrier.rotation_direction.is_some())
     }
 
+    #[test]
+    fn steer_to_respects_turn_rate() {
+        let mut carrier = Carrier::<usize>::new(0.0, 0.0);
+        carrier.set_turn_rate(0.5);
+        carrier.angle = 0.1;
+
+        // Shorter way leads through 0
+        carrier.steer_to(std::f64::consts::PI * 1.5, 1.0);
+        assert_abs_diff_eq!(carrier.angle, std::f64::consts::PI * 2.0 - 0.4);
+
+        carrier.steer_to(std::f64::consts::PI * 1.5, 1.0);
+        carrier.steer_to(std::f64::consts::PI * 1.5, 1.0);
+        carrier.steer_to(std::f64::consts::PI * 1.5, 1.0);
+        assert_abs_diff_eq!(carrier.angle, std::f64::consts::PI * 1.5);
+    }
+
+    #[test]
+    fn arc_speed_limit() {
+        let mut carrier = Carrier::<usize>::new(0.0, 0.0);
+        carrier.set_turn_rate(0.5);
+
+        // Straight ahead
+        assert!(carrier.arc_speed_limit((100.0, 0.0), 0.0).is_infinite());
+        // Behind, must turn in place
+        assert_abs_diff_eq!(
+            carrier.arc_speed_limit((-100.0, 0.0), std::f64::consts::PI),
+            0.0
+        );
+        // Right angle, the arc is a half circle with radius 50
+        assert_abs_diff_eq!(
+            carrier.arc_speed_limit((0.0, 100.0), std::f64::consts::FRAC_PI_2),
+            25.0,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn distance_to_stop() {
+        let mut carrier = Carrier::<usize>::new(0.0, 0.0);
+        carrier.set_deceleration(3.0);
+        carrier.speed = 9.0;
+
+        // 9 + 6 + 3 + 0
+        assert_eq!(carrier.calculate_tics_to_decelerate(1.0), 4);
+        assert_abs_diff_eq!(carrier.calculate_distance_to_stop(1.0), 18.0);
+
+        // 9 * 0.5 + 7.5 * 0.5 + ... + 0 * 0.5
+        assert_eq!(carrier.calculate_tics_to_decelerate(0.5), 7);
+        assert_abs_diff_eq!(carrier.calculate_distance_to_stop(0.5), 15.75);
+
+        // 10 + 7 + 4 + 1
+        carrier.speed = 10.0;
+        assert_eq!(carrier.calculate_tics_to_decelerate(1.0), 4);
+        assert_abs_diff_eq!(carrier.calculate_distance_to_stop(1.0), 22.0);
+
+        carrier.speed = 0.0;
+        assert_eq!(carrier.calculate_tics_to_decelerate(1.0), 1);
+        assert_abs_diff_eq!(carrier.calculate_distance_to_stop(1.0), 0.0);
+    }
 }
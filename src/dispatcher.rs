@@ -1,371 +1,908 @@
-use std::collections::HashMap;
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 
+use super::arena::*;
 use super::carrier::*;
+use super::error::*;
+use super::event::*;
+use super::navigation::*;
 use super::payload::*;
 use super::position::*;
 use super::slot::*;
+use super::spatial::*;
+use super::strategy::*;
 use super::tools::*;
 
-#[derive(Default)]
+pub(crate) fn change_cargo_balance<T: PartialEq + Eq + Hash + Copy>(
+    cargo_balance: &mut CargoBalance<T>,
+    cargo: T,
+    delta: i32,
+) {
+    let balance = cargo_balance.entry(cargo).or_insert(0);
+    *balance += delta;
+    if *balance == 0 {
+        cargo_balance.shift_remove(&cargo);
+    }
+}
+
+// Slots and cargo claimed by the tasks that are planned, but not handed out to the carriers yet.
+// Planner keeps them aside instead of marking the copy of all slots.
+pub(crate) struct Reservations<T: PartialEq + Eq + Hash + Copy> {
+    slots: HashSet<SlotId>,
+    // Change of the cargo balance, on top of the balance of the dispatcher
+    cargo_balance: CargoBalance<T>,
+}
+
+impl<T: PartialEq + Eq + Hash + Copy> Reservations<T> {
+    pub(crate) fn new() -> Self {
+        Reservations {
+            slots: HashSet::new(),
+            cargo_balance: CargoBalance::default(),
+        }
+    }
+
+    pub(crate) fn reserve(&mut self, slot: SlotId) {
+        self.slots.insert(slot);
+    }
+
+    pub(crate) fn change_cargo_balance(&mut self, cargo: T, delta: i32) {
+        change_cargo_balance(&mut self.cargo_balance, cargo, delta);
+    }
+
+    // Slot is neither taken care of by the carrier, nor reserved by the planned task,
+    // and carriers can get to it
+    pub(crate) fn is_free(&self, index: SlotId, slot: &Slot<T>) -> bool {
+        !slot.taken_care_of && !slot.unreachable && !self.slots.contains(&index)
+    }
+
+    fn balance(&self, cargo_balance: &CargoBalance<T>, cargo: T) -> i32 {
+        cargo_balance.get(&cargo).copied().unwrap_or(0)
+            + self.cargo_balance.get(&cargo).copied().unwrap_or(0)
+    }
+}
+
+// Returns the most urgent of the slots accepted by the `filter`, the first one if they are equally urgent
+pub(crate) fn find_most_urgent<T: PartialEq + Eq + Hash + Copy>(
+    slots: &Slots<T>,
+    filter: impl Fn(SlotId, &Slot<T>) -> bool,
+) -> Option<SlotId> {
+    slots
+        .iter()
+        .filter(|&(index, slot)| filter(index, slot))
+        .min_by(|(_, a), (_, b)| a.cmp_urgency(b))
+        .map(|(index, _)| index)
+}
+
+pub(crate) fn missing_cargo<T: PartialEq + Eq + Hash + Copy>(
+    cargo_balance: &CargoBalance<T>,
+    reserved: &Reservations<T>,
+) -> Option<T> {
+    cargo_balance
+        .keys()
+        .copied()
+        .find(|&cargo| reserved.balance(cargo_balance, cargo) < 0)
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Dispatcher<T: PartialEq + Eq + Hash + Copy> {
-    pub(crate) cargo_balance: HashMap<T, i32>,
-    pub(crate) slot_distances: HashMap<(usize, usize), f64>,
+    pub(crate) cargo_balance: CargoBalance<T>,
+    pub(crate) obstacles: Option<ObstacleGrid>,
+    // Lengths of the paths around the obstacles, measured from the slot on demand.
+    // Path lengths are measured again and the strategy must be set again after restoring.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    path_lengths: RefCell<HashMap<SlotId, HashMap<SlotId, f64>>>,
+    // Slot positions, separately for each kind of slot. Built when needed and kept
+    // up to date as slots are added and removed.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    slot_index: RefCell<HashMap<SlotKind, SpatialIndex>>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_strategy"))]
+    pub(crate) strategy: Box<dyn DispatchStrategy<T>>,
+}
+
+#[cfg(feature = "serde")]
+fn default_strategy<T: PartialEq + Eq + Hash + Copy>() -> Box<dyn DispatchStrategy<T>> {
+    Box::new(GreedyStrategy)
+}
+
+impl<T: PartialEq + Eq + Hash + Copy> Default for Dispatcher<T> {
+    fn default() -> Self {
+        Dispatcher::new()
+    }
 }
 
 impl<T: PartialEq + Eq + Hash + Copy> Dispatcher<T> {
     pub(crate) fn new() -> Self {
         Dispatcher {
-            cargo_balance: HashMap::new(),
-            slot_distances: HashMap::new(),
+            cargo_balance: CargoBalance::default(),
+            obstacles: None,
+            path_lengths: RefCell::new(HashMap::new()),
+            slot_index: RefCell::new(HashMap::new()),
+            strategy: Box::new(GreedyStrategy),
         }
     }
 
-    fn calculate_cargo_balance(&mut self, slots: &[Slot<T>]) {
+    // Counts the cargo from scratch. Besides the slots, it takes into account
+    // the payloads carried around and the tasks that carriers haven't started yet.
+    pub(crate) fn calculate_cargo_balance(&mut self, slots: &Slots<T>, carriers: &Carriers<T>) {
         self.cargo_balance.clear();
-
-        slots.iter().for_each(|x| {
-            let payloads = x.get_payloads();
-            for (i, _) in payloads.iter().enumerate() {
-                if let Some(payload) = payloads[i] {
-                    *self.cargo_balance.entry(payload.cargo).or_insert(0) += i as i32 * -2 + 1;
+        slots
+            .values()
+            .for_each(|slot| self.change_slot_balance(slot, 1));
+        for carrier in carriers.values() {
+            let carried = carrier
+                .payload
+                .iter()
+                .filter(|_| !carrier.carrying_to_pit)
+                .chain(
+                    carrier
+                        .hold
+                        .iter()
+                        .filter(|load| !load.to_pit)
+                        .map(|load| &load.payload),
+                );
+            for payload in carried {
+                self.increase_cargo_balance(payload.cargo);
+            }
+            // Same changes as when the task was planned
+            if carrier.payload.is_none() && !carrier.state.is_idle() {
+                if carrier.carrying_to_pit {
+                    if let Some(cargo) = carrier.pickup_cargo {
+                        self.reduce_cargo_balance(cargo);
+                    }
+                } else if let (true, Some(cargo)) = carrier.going_to_spawner {
+                    self.increase_cargo_balance(cargo);
                 }
             }
-        });
-        self.cargo_balance.retain(|_, v| *v != 0);
+        }
+    }
+
+    // Adds (`sign` = 1) or subtracts (`sign` = -1) the payloads of the slot to the balance
+    pub(crate) fn change_slot_balance(&mut self, slot: &Slot<T>, sign: i32) {
+        for payload in slot.get_contents() {
+            change_cargo_balance(&mut self.cargo_balance, payload.cargo, sign);
+        }
+        for cargo in slot.get_targets() {
+            change_cargo_balance(&mut self.cargo_balance, *cargo, -sign);
+        }
     }
 
-    pub(crate) fn get_distance_slot_slot(&self, s1: usize, s2: usize) -> f64 {
-        *self.slot_distances.get(&(s1, s2)).unwrap()
+    pub(crate) fn set_obstacles(&mut self, obstacles: Option<ObstacleGrid>) {
+        self.obstacles = obstacles;
+        self.path_lengths.get_mut().clear();
+    }
+
+    pub(crate) fn slot_added(&mut self, id: SlotId, slot: &Slot<T>) {
+        // Index that hasn't been built yet, including the one of the first slot of its kind,
+        // picks the slot up when it is built
+        if let Some(index) = self.slot_index.get_mut().get_mut(&slot.get_kind()) {
+            index.insert(id, *slot.get_position());
+        }
+    }
+
+    // Slots could have been changed in any way, so the index is built again when needed
+    pub(crate) fn slots_changed(&mut self) {
+        self.slot_index.get_mut().clear();
+    }
+
+    fn get_slot_index(&self, slots: &Slots<T>) -> Ref<'_, HashMap<SlotKind, SpatialIndex>> {
+        let outdated = {
+            let slot_index = self.slot_index.borrow();
+            slot_index.values().map(|index| index.len()).sum::<usize>() != slots.len()
+                || slot_index.values().any(|index| index.is_crowded())
+        };
+        if outdated {
+            let mut points: HashMap<SlotKind, Vec<(SlotId, Position)>> = HashMap::new();
+            slots.iter().for_each(|(id, slot)| {
+                points
+                    .entry(slot.get_kind())
+                    .or_default()
+                    .push((id, *slot.get_position()))
+            });
+            *self.slot_index.borrow_mut() = points
+                .into_iter()
+                .map(|(kind, points)| (kind, SpatialIndex::build(points)))
+                .collect();
+        }
+        self.slot_index.borrow()
+    }
+
+    pub(crate) fn get_distance_slot_slot(&self, slots: &Slots<T>, s1: SlotId, s2: SlotId) -> f64 {
+        let from = slots[s1].get_position();
+        let obstacles = match &self.obstacles {
+            Some(obstacles) => obstacles,
+            None => return distance_between_positions(from, slots[s2].get_position()),
+        };
+        let mut path_lengths = self.path_lengths.borrow_mut();
+        let row = path_lengths.entry(s1).or_default();
+        if !row.contains_key(&s2) {
+            // One search measures the paths to all slots, including the ones added
+            // since the last time
+            let positions: Vec<Position> =
+                slots.values().map(|slot| *slot.get_position()).collect();
+            row.extend(
+                slots
+                    .ids()
+                    .zip(obstacles.path_lengths_from(from, &positions)),
+            );
+        }
+        row[&s2]
     }
 
     pub(crate) fn get_distance_slot_position(
         &self,
-        slots: &[Slot<T>],
-        s: usize,
+        slots: &Slots<T>,
+        s: SlotId,
         pos: &Position,
     ) -> f64 {
         distance_between_positions(slots[s].get_position(), pos)
     }
 
-    fn calculate_slot_distances(&mut self, slots: &[Slot<T>]) {
-        slots.iter().enumerate().for_each(|(i1, v1)| {
-            slots.iter().enumerate().for_each(|(i2, v2)| {
-                self.slot_distances.insert(
-                    (i1, i2),
-                    distance_between_positions(v1.get_position(), v2.get_position()),
-                );
-            })
-        });
-    }
-
-    pub(crate) fn precalc(&mut self, slots: &[Slot<T>]) {
-        self.calculate_cargo_balance(slots);
-        self.calculate_slot_distances(slots);
-    }
-
-    pub(crate) fn conduct(&mut self, carriers: &mut Vec<Carrier<T>>, slots: &mut Vec<Slot<T>>) {
-        let mut _debug_carrier_indexer = 0;
-        carriers.iter_mut().for_each(|carrier| {
+    pub(crate) fn conduct(
+        &mut self,
+        carriers: &mut Carriers<T>,
+        slots: &mut Slots<T>,
+        events: &mut Vec<Event<T>>,
+    ) -> Result<(), SwarmError> {
+        for (index, carrier) in carriers.iter_mut() {
+            if carrier.state == State::LOOKINGFORTARGET && self.plan_route(index, carrier, slots)? {
+                continue;
+            }
             match carrier.state {
                 State::MOVING(target) => {
                     if let Some(payload) = carrier.payload {
                         if carrier.temporary_target {
-                            let mut ii: usize = 0;
-                            let is_another_slot =
-                                self.is_there_a_free_slot_for(payload, slots, &mut ii);
-                            if is_another_slot && ii != target {
-                                carrier.target_slot(
-                                    ii,
-                                    &mut slots[ii],
-                                    false,
-                                    false,
-                                    (false, None),
-                                );
-                                slots[target].taken_care_of = false;
+                            match self.is_there_a_free_slot_for(
+                                payload,
+                                slots,
+                                &Reservations::new(),
+                            ) {
+                                Some(another) if another != target => {
+                                    carrier.target_slot(
+                                        index,
+                                        another,
+                                        &mut slots[another],
+                                        false,
+                                        false,
+                                        (false, None),
+                                    )?;
+                                    slot_at(slots, index, target)?.taken_care_of = false;
+                                    events.push(Event::Rerouted {
+                                        carrier: index,
+                                        from: target,
+                                        to: another,
+                                    });
+                                }
+                                _ => {}
                             }
                         }
                     }
                 }
-                State::IDLE => {
-                    if let Some(slot_index) =
-                        self.find_slot_with_payload_that_should_go_to_the_pit(slots)
-                    {
-                        if let Some(pit_index) = self.find_closest_object(
-                            slots,
-                            slots[slot_index].get_position(),
-                            |slot| slot.is_pit(),
-                        ) {
-                            carrier.target_slot(
-                                slot_index,
-                                &mut slots[slot_index],
-                                false,
-                                true,
-                                (false, None),
-                            );
-                            carrier.reserved_target = Some(pit_index);
-                            self.reduce_cargo_balance(
-                                slots[slot_index].get_payloads()[0].unwrap().cargo,
-                            );
-                        }
-                    } else if let (Some(slot_index), possible_target) =
-                        self.find_slot_with_mismatched_payload_and_free_target(slots)
-                    {
-                        carrier.target_slot(
-                            slot_index,
-                            &mut slots[slot_index],
-                            false,
-                            false,
-                            (false, None),
-                        );
-                        slots[possible_target].taken_care_of = true;
-                        carrier.reserved_target = Some(possible_target);
-                    } else if let Some(slot_index) = self.find_slot_with_mismatched_payload(slots) {
+                State::LOOKINGFORTARGET => match carrier.reserved_target {
+                    Some(slot_index) => {
+                        let slot = slot_at(slots, index, slot_index)?;
                         carrier.target_slot(
+                            index,
                             slot_index,
-                            &mut slots[slot_index],
-                            false,
-                            false,
-                            (false, None),
-                        );
-                    } else if let Some(cargo) = self.get_cargo_to_spawn() {
-                        if let Some(slot_index) =
-                            self.find_closest_object(slots, carrier.get_position(), |slot| {
-                                slot.is_spawner()
-                            })
-                        {
-                            carrier.target_slot(
-                                slot_index,
-                                &mut slots[slot_index],
-                                false,
-                                false,
-                                (true, Some(cargo)),
-                            );
-                        }
+                            slot,
+                            carrier.temporary_target,
+                            carrier.carrying_to_pit,
+                            carrier.going_to_spawner,
+                        )?
                     }
-                }
-                State::LOOKINGFORTARGET => match carrier.reserved_target {
-                    Some(slot_index) => carrier.target_slot(
-                        slot_index,
-                        &mut slots[slot_index],
-                        carrier.temporary_target,
-                        carrier.carrying_to_pit,
-                        carrier.going_to_spawner,
-                    ),
                     None => match self.find_slot_for_target(slots, carrier.payload) {
                         Some(slot_index) => carrier.target_slot(
+                            index,
                             slot_index,
                             &mut slots[slot_index],
                             carrier.temporary_target,
                             carrier.carrying_to_pit,
                             carrier.going_to_spawner,
-                        ),
+                        )?,
+                        None if carrier.payload.is_none() => {
+                            return Err(SwarmError::NoPayload { carrier: index })
+                        }
                         None => {
                             carrier.state = State::NOTARGET;
                         }
                     },
                 },
-                State::NOTARGET => match self.find_temporary_slot(slots, carrier.payload) {
-                    Some(slot_index) => {
-                        carrier.target_slot(
-                            slot_index,
-                            &mut slots[slot_index],
-                            true,
-                            false,
-                            (false, None),
-                        );
-                    }
-                    None => {
-                        carrier.state = State::LOOKINGFORTARGET;
+                State::NOTARGET => {
+                    match self.find_temporary_slot(slots, carrier.payload, carrier.get_position()) {
+                        Some(slot_index) => {
+                            carrier.target_slot(
+                                index,
+                                slot_index,
+                                &mut slots[slot_index],
+                                true,
+                                false,
+                                (false, None),
+                            )?;
+                        }
+                        None if carrier.payload.is_none() => {
+                            return Err(SwarmError::NoPayload { carrier: index })
+                        }
+                        None => {
+                            carrier.state = State::LOOKINGFORTARGET;
+                        }
                     }
-                },
+                }
                 _ => {}
             };
-            _debug_carrier_indexer += 1
-        });
+        }
+        if carriers.values().any(|carrier| carrier.state.is_idle()) {
+            // Strategy is taken out for a moment, so the context can borrow the dispatcher
+            let mut strategy = std::mem::replace(&mut self.strategy, Box::new(GreedyStrategy));
+            let assignments = strategy.assign(&DispatchContext::new(carriers, slots, self));
+            self.strategy = strategy;
+            for assignment in assignments {
+                self.apply_assignment(assignment, carriers, slots)?;
+            }
+        }
+        Ok(())
     }
 
-    fn get_cargo_to_spawn(&mut self) -> Option<T> {
-        if self.cargo_balance.is_empty() {
-            return None;
+    fn apply_assignment(
+        &mut self,
+        assignment: Assignment<T>,
+        carriers: &mut Carriers<T>,
+        slots: &mut Slots<T>,
+    ) -> Result<(), SwarmError> {
+        let index = assignment.carrier;
+        let invalid = SwarmError::InvalidAssignment { carrier: index };
+        let carrier = match carriers.get_mut(index) {
+            Some(carrier) if carrier.state.is_idle() => carrier,
+            _ => return Err(invalid),
+        };
+        let is_pickup_possible = |slot: SlotId| match slots.get(slot) {
+            Some(slot) => !slot.taken_care_of && !slot.contents.is_empty(),
+            None => false,
+        };
+
+        match assignment.task {
+            Task::ToPit { slot, pit } => {
+                if !is_pickup_possible(slot) || !matches!(slots.get(pit), Some(s) if s.is_pit()) {
+                    return Err(invalid);
+                }
+                let cargo = self.cargo_to_pick(&slots[slot], None, true);
+                carrier.target_slot(index, slot, &mut slots[slot], false, true, (false, None))?;
+                carrier.reserved_target = Some(pit);
+                carrier.pickup_cargo = cargo;
+                if let Some(cargo) = cargo {
+                    self.reduce_cargo_balance(cargo);
+                }
+            }
+            Task::Transfer { slot, target } => {
+                if !is_pickup_possible(slot) {
+                    return Err(invalid);
+                }
+                if let Some(target) = target {
+                    match slots.get(target) {
+                        Some(s) if target != slot && !s.taken_care_of && !s.is_spawner() => (),
+                        _ => return Err(invalid),
+                    }
+                }
+                let cargo = self.cargo_to_pick(&slots[slot], target.map(|t| &slots[t]), false);
+                carrier.target_slot(index, slot, &mut slots[slot], false, false, (false, None))?;
+                if let Some(target) = target {
+                    slots[target].taken_care_of = true;
+                }
+                carrier.reserved_target = target;
+                carrier.pickup_cargo = cargo;
+            }
+            Task::Spawn { cargo } => {
+                let slot = self
+                    .find_closest_of_kind(slots, carrier.get_position(), SlotKind::SPAWNER, |_| {
+                        true
+                    })
+                    .ok_or(invalid)?;
+                carrier.target_slot(
+                    index,
+                    slot,
+                    &mut slots[slot],
+                    false,
+                    false,
+                    (true, Some(cargo)),
+                )?;
+                self.increase_cargo_balance(cargo);
+            }
+        }
+        Ok(())
+    }
+
+    // Carrier that has room for more goes for another payload on the way,
+    // provided that both payloads have a place to go. Otherwise it delivers
+    // the payload with the closest target first. Returns `true` if the carrier
+    // went for another payload.
+    fn plan_route(
+        &mut self,
+        index: CarrierId,
+        carrier: &mut Carrier<T>,
+        slots: &mut Slots<T>,
+    ) -> Result<bool, SwarmError> {
+        if carrier.has_room() && carrier.payload.is_some() && !carrier.temporary_target {
+            if carrier.reserved_target.is_none() && !carrier.carrying_to_pit {
+                if let Some(target) = self.find_slot_for_target(slots, carrier.payload) {
+                    slots[target].taken_care_of = true;
+                    carrier.reserved_target = Some(target);
+                }
+            }
+            if carrier.reserved_target.is_some() {
+                if let Some((slot, cargo, target, to_pit)) =
+                    self.find_batch_pickup(slots, carrier.get_position())
+                {
+                    carrier.stash_payload();
+                    carrier.target_slot(
+                        index,
+                        slot,
+                        &mut slots[slot],
+                        false,
+                        to_pit,
+                        (false, None),
+                    )?;
+                    carrier.reserved_target = Some(target);
+                    carrier.pickup_cargo = Some(cargo);
+                    if to_pit {
+                        self.reduce_cargo_balance(cargo);
+                    } else {
+                        slots[target].taken_care_of = true;
+                    }
+                    return Ok(true);
+                }
+            }
+        }
+
+        if !carrier.hold.is_empty() {
+            let pos = *carrier.get_position();
+            let distance = |target: Option<SlotId>| {
+                target.map_or(f64::INFINITY, |target| {
+                    self.get_distance_slot_position(slots, target, &pos)
+                })
+            };
+            let current = distance(carrier.reserved_target);
+            let closest = carrier
+                .hold
+                .iter()
+                .enumerate()
+                .map(|(i, load)| (i, distance(load.target)))
+                .min_by(|a, b| (a.1).partial_cmp(&b.1).unwrap());
+            if let Some((i, _)) = closest.filter(|&(_, d)| d < current) {
+                carrier.stash_payload();
+                carrier.unstash_payload(i);
+            }
         }
-        let missing = *self.cargo_balance.keys().next().unwrap();
-        self.increase_cargo_balance(missing);
-        Some(missing)
+        Ok(false)
+    }
+
+    // Finds the payload, closest to the position, that is worth picking up:
+    // it either has a free target or should go to the pit
+    fn find_batch_pickup(
+        &self,
+        slots: &Slots<T>,
+        pos: &Position,
+    ) -> Option<(SlotId, T, SlotId, bool)> {
+        slots
+            .iter()
+            .filter(|(_, slot)| !slot.taken_care_of)
+            .filter_map(|(index, slot)| {
+                let pickup = slot.surplus().into_iter().find_map(|cargo| {
+                    if self.is_excessive(cargo) {
+                        let pit = self.find_closest_of_kind(
+                            slots,
+                            slot.get_position(),
+                            SlotKind::PIT,
+                            |_| true,
+                        )?;
+                        Some((index, cargo, pit, true))
+                    } else {
+                        let target = self.is_there_a_free_slot_for(
+                            Payload::new(cargo),
+                            slots,
+                            &Reservations::new(),
+                        )?;
+                        Some((index, cargo, target, false))
+                    }
+                })?;
+                Some((pickup, self.get_distance_slot_position(slots, index, pos)))
+            })
+            .min_by(|a, b| (a.1).partial_cmp(&b.1).unwrap())
+            .map(|(pickup, _)| pickup)
+    }
+
+    fn is_excessive(&self, cargo: T) -> bool {
+        self.cargo_balance
+            .get(&cargo)
+            .is_some_and(|&balance| balance > 0)
+    }
+
+    // Decides which cargo should be taken from the slot: the excessive one if it
+    // goes to the pit, the one needed by the target, or any that is in surplus
+    fn cargo_to_pick(&self, slot: &Slot<T>, target: Option<&Slot<T>>, to_pit: bool) -> Option<T> {
+        let surplus = slot.surplus();
+        let preferred = if to_pit {
+            surplus.iter().find(|&&cargo| self.is_excessive(cargo))
+        } else {
+            target.and_then(|target| {
+                surplus
+                    .iter()
+                    .find(|&&cargo| target.accepts(&Payload::new(cargo)))
+            })
+        };
+        preferred
+            .or_else(|| surplus.first())
+            .copied()
+            .or_else(|| slot.contents.last().map(|p| p.cargo))
     }
 
     fn reduce_cargo_balance(&mut self, cargo: T) {
-        *self.cargo_balance.entry(cargo).or_insert(0) -= 1;
-        self.cargo_balance.retain(|_, v| *v != 0);
+        change_cargo_balance(&mut self.cargo_balance, cargo, -1);
     }
 
     fn increase_cargo_balance(&mut self, cargo: T) {
-        *self.cargo_balance.entry(cargo).or_insert(0) += 1;
-        self.cargo_balance.retain(|_, v| *v != 0);
+        change_cargo_balance(&mut self.cargo_balance, cargo, 1);
     }
 
-    fn find_closest_object(
-        &self,
-        slots: &[Slot<T>],
-        pos: &Position,
-        classifier: fn(&Slot<T>) -> bool,
-    ) -> Option<usize> {
-        let mut distances = Vec::new();
-        slots.iter().enumerate().for_each(|(i, v)| {
-            if classifier(&v) {
-                distances.push((i, self.get_distance_slot_position(slots, i, pos)));
+    // Reverts the cargo balance change made when the task that
+    // the carrier hasn't started yet (no payload picked up) was planned
+    fn revert_planned_pickup(&mut self, carrier: &Carrier<T>) {
+        if carrier.payload.is_some() {
+            return;
+        }
+        if carrier.carrying_to_pit {
+            if let Some(cargo) = carrier.pickup_cargo {
+                self.increase_cargo_balance(cargo);
             }
-        });
-        if distances.is_empty() {
-            return None;
-        };
-        Some(
-            distances
-                .iter()
-                .min_by(|a, b| (a.1).partial_cmp(&b.1).unwrap())
-                .unwrap()
-                .0,
-        )
+        } else if let (true, Some(cargo)) = carrier.going_to_spawner {
+            self.reduce_cargo_balance(cargo);
+        }
     }
 
-    fn find_slot_with_payload_that_should_go_to_the_pit(&self, slots: &[Slot<T>]) -> Option<usize> {
-        let excessive = self.cargo_balance.iter().find(|&(_, &v)| v > 0);
-        if let Some(cargo) = excessive {
-            if let Some(slot_index) = self.find_mismatched_slot_that_contains(slots, *cargo.0) {
-                if !slots[slot_index].taken_care_of {
-                    return Some(slot_index);
+    pub(crate) fn carrier_removed(&mut self, carrier: &Carrier<T>, slots: &mut Slots<T>) {
+        if carrier.state.is_idle() {
+            return;
+        }
+        self.revert_planned_pickup(carrier);
+        if let Some(payload) = carrier.payload {
+            if !carrier.carrying_to_pit {
+                // Payload leaves the swarm together with the carrier
+                self.reduce_cargo_balance(payload.cargo);
+            }
+        }
+        for load in &carrier.hold {
+            if !load.to_pit {
+                self.reduce_cargo_balance(load.payload.cargo);
+            }
+        }
+        for slot in carrier
+            .state
+            .slot()
+            .into_iter()
+            .chain(carrier.reserved_target)
+            .chain(carrier.hold.iter().filter_map(|load| load.target))
+        {
+            if let Some(slot) = slots.get_mut(slot) {
+                slot.taken_care_of = false;
+            }
+        }
+    }
+
+    // Called before the slot is actually removed, so the carriers
+    // can still look at it while re-planning
+    pub(crate) fn slot_removed(
+        &mut self,
+        removed: SlotId,
+        carriers: &mut Carriers<T>,
+        slots: &mut Slots<T>,
+    ) {
+        self.release_slot(removed, carriers, slots);
+        for (_, carrier) in carriers.iter_mut() {
+            for payload in carrier
+                .payload
+                .iter_mut()
+                .chain(carrier.hold.iter_mut().map(|load| &mut load.payload))
+            {
+                if payload.taken_from == Some(removed) {
+                    payload.taken_from = None;
                 }
             }
         }
-        None
+
+        self.change_slot_balance(&slots[removed], -1);
+        self.path_lengths.get_mut().remove(&removed);
+        if let Some(index) = self
+            .slot_index
+            .get_mut()
+            .get_mut(&slots[removed].get_kind())
+        {
+            index.remove(removed, slots[removed].get_position());
+        }
     }
 
-    fn find_mismatched_slot_that_contains(&self, slots: &[Slot<T>], cargo: T) -> Option<usize> {
-        for (i, v) in slots.iter().enumerate() {
-            if !v.taken_care_of {
-                let [current, target] = v.get_payloads();
-                if current != target {
-                    if let Some(contained_cargo) = current {
-                        if contained_cargo.cargo == cargo {
-                            return Some(i);
+    // Carriers that can't get to the slot give up on it and the slot is skipped from now on
+    pub(crate) fn slot_unreachable(
+        &mut self,
+        unreachable: SlotId,
+        carriers: &mut Carriers<T>,
+        slots: &mut Slots<T>,
+    ) {
+        slots[unreachable].unreachable = true;
+        self.release_slot(unreachable, carriers, slots);
+        slots[unreachable].taken_care_of = false;
+    }
+
+    // Carriers that were going to pick up from the slot become idle, the ones
+    // that were bringing payload to the slot look for another target
+    fn release_slot(&mut self, removed: SlotId, carriers: &mut Carriers<T>, slots: &mut Slots<T>) {
+        for (_, carrier) in carriers.iter_mut() {
+            if carrier.state.is_idle() {
+                continue;
+            }
+            if carrier.state.slot() == Some(removed) {
+                if carrier.payload.is_some() {
+                    carrier.state = State::LOOKINGFORTARGET;
+                    carrier.temporary_target = false;
+                } else {
+                    self.revert_planned_pickup(carrier);
+                    if let Some(reserved) = carrier.reserved_target.and_then(|r| slots.get_mut(r)) {
+                        reserved.taken_care_of = false;
+                    }
+                    carrier.reserved_target = None;
+                    carrier.carrying_to_pit = false;
+                    carrier.going_to_spawner = (false, None);
+                    if carrier.hold.is_empty() {
+                        carrier.state = State::IDLE;
+                        continue;
+                    }
+                    // Deliver what has already been picked up
+                    carrier.unstash_payload(0);
+                    carrier.state = State::LOOKINGFORTARGET;
+                }
+            }
+            if carrier.reserved_target == Some(removed) {
+                carrier.reserved_target = None;
+                if carrier.carrying_to_pit {
+                    self.find_another_pit(carrier, removed, slots);
+                }
+            }
+            let pos = *carrier.get_position();
+            for load in carrier.hold.iter_mut() {
+                if load.target == Some(removed) {
+                    load.target = None;
+                    if load.to_pit {
+                        load.to_pit = false;
+                        match self.find_closest_pit(removed, &pos, slots) {
+                            Some(pit) => {
+                                load.target = Some(pit);
+                                load.to_pit = true;
+                            }
+                            None => self.increase_cargo_balance(load.payload.cargo),
                         }
                     }
                 }
             }
         }
+    }
+
+    // Carrier that was heading to the removed pit goes to the closest
+    // remaining one. If there is none, the payload is no longer considered
+    // excessive and will be delivered like any other.
+    fn find_another_pit(&mut self, carrier: &mut Carrier<T>, removed: SlotId, slots: &Slots<T>) {
+        match self.find_closest_pit(removed, carrier.get_position(), slots) {
+            Some(pit) => carrier.reserved_target = Some(pit),
+            None => {
+                carrier.carrying_to_pit = false;
+                let cargo = match carrier.payload {
+                    Some(payload) => Some(payload.cargo),
+                    None => carrier.pickup_cargo,
+                };
+                if let Some(cargo) = cargo {
+                    self.increase_cargo_balance(cargo);
+                }
+            }
+        }
+    }
+
+    fn find_closest_pit(
+        &self,
+        removed: SlotId,
+        pos: &Position,
+        slots: &Slots<T>,
+    ) -> Option<SlotId> {
+        self.find_closest_of_kind(slots, pos, SlotKind::PIT, |id| id != removed)
+    }
+
+    pub(crate) fn find_closest_of_kind<F: FnMut(SlotId) -> bool>(
+        &self,
+        slots: &Slots<T>,
+        pos: &Position,
+        kind: SlotKind,
+        mut filter: F,
+    ) -> Option<SlotId> {
+        self.get_slot_index(slots)
+            .get(&kind)?
+            .nearest(pos, |id| !slots[id].unreachable && filter(id))
+            .map(|(id, _)| id)
+    }
+
+    pub(crate) fn find_closest_object(
+        &self,
+        slots: &Slots<T>,
+        pos: &Position,
+        classifier: fn(&Slot<T>) -> bool,
+    ) -> Option<SlotId> {
+        self.get_slot_index(slots)
+            .values()
+            .filter_map(|index| {
+                index.nearest(pos, |id| !slots[id].unreachable && classifier(&slots[id]))
+            })
+            .min_by(|a, b| (a.1).partial_cmp(&b.1).unwrap().then(a.0.cmp(&b.0)))
+            .map(|(id, _)| id)
+    }
+
+    pub(crate) fn find_slot_with_payload_that_should_go_to_the_pit(
+        &self,
+        slots: &Slots<T>,
+        reserved: &Reservations<T>,
+    ) -> Option<(SlotId, T)> {
+        let excessive = self
+            .cargo_balance
+            .keys()
+            .copied()
+            .find(|&cargo| reserved.balance(&self.cargo_balance, cargo) > 0);
+        if let Some(cargo) = excessive {
+            if let Some(slot_index) =
+                self.find_mismatched_slot_that_contains(slots, cargo, reserved)
+            {
+                return Some((slot_index, cargo));
+            }
+        }
         None
     }
 
+    fn find_mismatched_slot_that_contains(
+        &self,
+        slots: &Slots<T>,
+        cargo: T,
+        reserved: &Reservations<T>,
+    ) -> Option<SlotId> {
+        find_most_urgent(slots, |i, v| reserved.is_free(i, v) && v.has_surplus(cargo))
+    }
+
     fn is_there_a_free_slot_for(
         &self,
         payload: Payload<T>,
-        slots: &[Slot<T>],
-        ii: &mut usize,
-    ) -> bool {
-        for (i, v) in slots.iter().enumerate() {
-            let [current, target] = v.get_payloads();
-            if current == None && target != None && !v.taken_care_of && target.unwrap() == payload {
-                *ii = i;
-                return true;
+        slots: &Slots<T>,
+        reserved: &Reservations<T>,
+    ) -> Option<SlotId> {
+        find_most_urgent(slots, |i, v| reserved.is_free(i, v) && v.accepts(&payload))
+    }
+
+    // Pair of the mismatched payload and the most urgent free target for it
+    pub(crate) fn find_slot_with_mismatched_payload_and_free_target(
+        &self,
+        slots: &Slots<T>,
+        reserved: &Reservations<T>,
+    ) -> Option<(SlotId, SlotId)> {
+        // First free slot that holds each kind of the surplus cargo
+        let mut sources: HashMap<T, SlotId> = HashMap::new();
+        for (i, x) in slots.iter().filter(|&(i, x)| reserved.is_free(i, x)) {
+            for payload in x.get_contents() {
+                if x.has_surplus(payload.cargo) {
+                    sources.entry(payload.cargo).or_insert(i);
+                }
             }
         }
+        if sources.is_empty() {
+            return None;
+        }
+        let source_for = |slot: &Slot<T>| {
+            slot.get_targets().iter().find_map(|&cargo| {
+                sources
+                    .get(&cargo)
+                    .copied()
+                    .filter(|_| slot.accepts(&Payload::new(cargo)))
+            })
+        };
+        let target = find_most_urgent(slots, |i, v| {
+            reserved.is_free(i, v) && source_for(v).is_some()
+        })?;
+        Some((source_for(&slots[target])?, target))
+    }
 
-        false
+    pub(crate) fn find_slot_with_mismatched_payload(
+        &self,
+        slots: &Slots<T>,
+        reserved: &Reservations<T>,
+    ) -> Option<SlotId> {
+        find_most_urgent(slots, |i, x| reserved.is_free(i, x) && x.has_any_surplus())
     }
 
-    fn find_slot_with_mismatched_payload_and_free_target(
+    pub(crate) fn find_slot_for_target(
         &self,
-        slots: &[Slot<T>],
-    ) -> (Option<usize>, usize) {
-        let mut ii: usize = 0; // TODO: Make this an Option
-        let found = slots.iter().position(|x| {
-            let [current, target] = x.get_payloads();
-            current != None
-                && current != target
-                && !x.taken_care_of
-                && self.is_there_a_free_slot_for(current.unwrap(), slots, &mut ii)
-        });
-
-        (found, ii)
-    }
-
-    fn find_slot_with_mismatched_payload(&self, slots: &[Slot<T>]) -> Option<usize> {
-        slots.iter().position(|x| {
-            let [current, target] = x.get_payloads();
-            current != None && current != target && !x.taken_care_of
+        slots: &Slots<T>,
+        target_payload: Option<Payload<T>>,
+    ) -> Option<SlotId> {
+        let t = target_payload?;
+        find_most_urgent(slots, |index, slot| {
+            slot.accepts(&t)
+                && !slot.taken_care_of
+                && !slot.unreachable
+                && t.taken_from != Some(index)
         })
     }
 
-    fn find_slot_for_target(
+    // Cargo needed by the most urgent slot, or any missing cargo if no slot can take it now
+    pub(crate) fn find_missing_cargo(
         &self,
-        slots: &[Slot<T>],
-        target_payload: Option<Payload<T>>,
-    ) -> Option<usize> {
-        let t = target_payload.expect("Trying to find slot for empty target");
-
-        if let Some((index, _)) = slots.iter().enumerate().find(|(index, _)| {
-            let [current, _] = slots[*index].get_payloads();
-            current == None
-                && slots[*index].accepts(target_payload)
-                && !slots[*index].taken_care_of
-                && t.taken_from != Some(*index)
-        }) {
-            Some(index)
-        } else {
-            None
-        }
+        slots: &Slots<T>,
+        reserved: &Reservations<T>,
+    ) -> Option<T> {
+        let is_missing = |cargo: T| reserved.balance(&self.cargo_balance, cargo) < 0;
+        let needed = |slot: &Slot<T>| {
+            slot.get_targets()
+                .iter()
+                .copied()
+                .find(|&cargo| is_missing(cargo) && slot.accepts(&Payload::new(cargo)))
+        };
+        find_most_urgent(slots, |index, slot| {
+            reserved.is_free(index, slot) && needed(slot).is_some()
+        })
+        .and_then(|index| needed(&slots[index]))
+        .or_else(|| missing_cargo(&self.cargo_balance, reserved))
     }
 
     fn is_candidate_for_temporary_slot(
         &self,
-        slots: &[Slot<T>],
-        index: usize,
+        slots: &Slots<T>,
+        index: SlotId,
         target: Payload<T>,
     ) -> bool {
-        slots[index].current_payload == None
+        slots[index].has_room()
             && !slots[index].is_pit()
             && !slots[index].is_spawner()
             && !slots[index].taken_care_of
+            && !slots[index].unreachable
             && target.taken_from != Some(index)
     }
 
-    fn _find_any_temporary_slot(&self, slots: &[Slot<T>], target: Payload<T>) -> Option<usize> {
-        if let Some((index, _)) = slots
-            .iter()
-            .enumerate()
-            .find(|(index, _)| self.is_candidate_for_temporary_slot(slots, *index, target))
-        {
-            Some(index)
-        } else {
-            None
-        }
+    fn _find_any_temporary_slot(&self, slots: &Slots<T>, target: Payload<T>) -> Option<SlotId> {
+        slots
+            .ids()
+            .find(|index| self.is_candidate_for_temporary_slot(slots, *index, target))
     }
 
-    fn find_closest_temporary_slot(&self, slots: &[Slot<T>], target: Payload<T>) -> Option<usize> {
-        let mut distances = Vec::new();
-        slots.iter().enumerate().for_each(|(i, _)| {
-            if self.is_candidate_for_temporary_slot(slots, i, target) {
-                distances.push((
-                    i,
-                    self.get_distance_slot_slot(i, target.taken_from.unwrap()),
-                ));
+    fn find_closest_temporary_slot(
+        &self,
+        slots: &Slots<T>,
+        target: Payload<T>,
+        pos: &Position,
+    ) -> Option<SlotId> {
+        // Slot the payload was taken from may have been removed in the meantime
+        let from = match target.taken_from {
+            Some(taken_from) if self.obstacles.is_some() => {
+                // Paths around the obstacles are not indexed
+                return slots
+                    .ids()
+                    .filter(|&i| self.is_candidate_for_temporary_slot(slots, i, target))
+                    .map(|i| (i, self.get_distance_slot_slot(slots, i, taken_from)))
+                    .min_by(|a, b| (a.1).partial_cmp(&b.1).unwrap())
+                    .map(|(i, _)| i);
             }
-        });
-        if distances.is_empty() {
-            return None;
+            Some(taken_from) => slots[taken_from].get_position(),
+            None => pos,
         };
-        Some(
-            distances
-                .iter()
-                .min_by(|a, b| (a.1).partial_cmp(&b.1).unwrap())
-                .unwrap()
-                .0,
-        )
+        self.find_closest_of_kind(slots, from, SlotKind::CLASSIC, |i| {
+            self.is_candidate_for_temporary_slot(slots, i, target)
+        })
     }
 
-    fn find_temporary_slot(&self, slots: &[Slot<T>], target: Option<Payload<T>>) -> Option<usize> {
-        let t = target.expect("Trying to find slot for empty target");
-        self.find_closest_temporary_slot(slots, t)
+    fn find_temporary_slot(
+        &self,
+        slots: &Slots<T>,
+        target: Option<Payload<T>>,
+        pos: &Position,
+    ) -> Option<SlotId> {
+        self.find_closest_temporary_slot(slots, target?, pos)
     }
 }
 
@@ -373,10 +910,14 @@ impl<T: PartialEq + Eq + Hash + Copy> Dispatcher<T> {
 mod tests {
     use crate::dispatcher::*;
 
+    fn id(index: usize) -> SlotId {
+        SlotId::from_parts(index, 0)
+    }
+
     #[test]
-    fn find_slot_for_target() {
+    pub(crate) fn find_slot_for_target() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -391,18 +932,66 @@ mod tests {
                 Some(Payload::new('B')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
             dispatcher.find_slot_for_target(&slots, Some(Payload::new('B'))),
-            Some(1)
+            Some(id(1))
         )
     }
 
+    #[test]
+    fn most_urgent_slot_is_served_first() {
+        let dispatcher = Dispatcher::new();
+        let target = |priority, deadline| {
+            let mut slot = Slot::new(
+                100.0,
+                100.0,
+                None,
+                Some(Payload::new('A')),
+                SlotKind::CLASSIC,
+            );
+            slot.set_priority(priority);
+            slot.set_deadline(deadline);
+            slot
+        };
+        let mut slots: Slots<char> = vec![
+            Slot::new(0.0, 0.0, Some(Payload::new('A')), None, SlotKind::CLASSIC),
+            target(0, None),
+            target(0, Some(50.0)),
+            target(1, None),
+            target(0, Some(20.0)),
+        ]
+        .into_iter()
+        .collect();
+
+        for expected in [3, 4, 2, 1].iter() {
+            assert_eq!(
+                dispatcher.find_slot_for_target(&slots, Some(Payload::new('A'))),
+                Some(id(*expected))
+            );
+            assert_eq!(
+                dispatcher.find_slot_with_mismatched_payload_and_free_target(
+                    &slots,
+                    &Reservations::new()
+                ),
+                Some((id(0), id(*expected)))
+            );
+            slots[id(*expected)].taken_care_of = true;
+        }
+        assert_eq!(
+            dispatcher
+                .find_slot_with_mismatched_payload_and_free_target(&slots, &Reservations::new()),
+            None
+        );
+    }
+
     #[test]
     fn find_mismatched_slot1() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -417,16 +1006,21 @@ mod tests {
                 Some(Payload::new('Y')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         // Slot without current payload cannot have mismatched payload
-        assert_eq!(dispatcher.find_slot_with_mismatched_payload(&slots), None)
+        assert_eq!(
+            dispatcher.find_slot_with_mismatched_payload(&slots, &Reservations::new()),
+            None
+        )
     }
 
     #[test]
     fn find_mismatched_slot2() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -441,18 +1035,20 @@ mod tests {
                 Some(Payload::new('Y')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
-            dispatcher.find_slot_with_mismatched_payload(&slots),
-            Some(1)
+            dispatcher.find_slot_with_mismatched_payload(&slots, &Reservations::new()),
+            Some(id(1))
         )
     }
 
     #[test]
     fn find_mismatched_slot3() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -467,15 +1063,20 @@ mod tests {
                 Some(Payload::new('A')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
-        assert_eq!(dispatcher.find_slot_with_mismatched_payload(&slots), None)
+        assert_eq!(
+            dispatcher.find_slot_with_mismatched_payload(&slots, &Reservations::new()),
+            None
+        )
     }
 
     #[test]
     fn find_mismatched_slot_with_target1() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -490,18 +1091,21 @@ mod tests {
                 Some(Payload::new('B')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
-            dispatcher.find_slot_with_mismatched_payload_and_free_target(&slots),
-            (None, 0)
+            dispatcher
+                .find_slot_with_mismatched_payload_and_free_target(&slots, &Reservations::new()),
+            None
         )
     }
 
     #[test]
     fn find_mismatched_slot_with_target2() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -516,18 +1120,21 @@ mod tests {
                 Some(Payload::new('B')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
-            dispatcher.find_slot_with_mismatched_payload_and_free_target(&slots),
-            (None, 0)
+            dispatcher
+                .find_slot_with_mismatched_payload_and_free_target(&slots, &Reservations::new()),
+            None
         )
     }
 
     #[test]
     fn find_mismatched_slot_with_target3() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -542,18 +1149,21 @@ mod tests {
                 Some(Payload::new('A')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
-            dispatcher.find_slot_with_mismatched_payload_and_free_target(&slots),
-            (Some(0), 1)
+            dispatcher
+                .find_slot_with_mismatched_payload_and_free_target(&slots, &Reservations::new()),
+            Some((id(0), id(1)))
         )
     }
 
     #[test]
     fn is_there_a_free_slot_for1() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -568,22 +1178,21 @@ mod tests {
                 Some(Payload::new('C')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         let p = Payload::new('C');
-        let mut ii = 0;
-
         assert_eq!(
-            dispatcher.is_there_a_free_slot_for(p, &slots, &mut ii),
-            true
+            dispatcher.is_there_a_free_slot_for(p, &slots, &Reservations::new()),
+            Some(id(1))
         );
-        assert_eq!(ii, 1)
     }
 
     #[test]
     fn is_there_a_free_slot_for2() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -598,21 +1207,21 @@ mod tests {
                 Some(Payload::new('D')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         let p = Payload::new('C');
-        let mut ii = 0;
-
         assert_eq!(
-            dispatcher.is_there_a_free_slot_for(p, &slots, &mut ii),
-            false
+            dispatcher.is_there_a_free_slot_for(p, &slots, &Reservations::new()),
+            None
         );
     }
 
     #[test]
     fn calculate_cargo_balance() {
         let mut dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -655,9 +1264,11 @@ mod tests {
                 Some(Payload::new('G')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
-        dispatcher.calculate_cargo_balance(&slots);
+        dispatcher.calculate_cargo_balance(&slots, &Carriers::new());
 
         assert_eq!(dispatcher.cargo_balance.get(&'A'), None);
         assert_eq!(dispatcher.cargo_balance.get(&'C'), None);
@@ -669,7 +1280,7 @@ mod tests {
     #[test]
     fn reduce_cargo_balance() {
         let mut dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -712,9 +1323,11 @@ mod tests {
                 Some(Payload::new('G')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
-        dispatcher.calculate_cargo_balance(&slots);
+        dispatcher.calculate_cargo_balance(&slots, &Carriers::new());
         dispatcher.reduce_cargo_balance('G');
         dispatcher.reduce_cargo_balance('E');
 
@@ -725,10 +1338,63 @@ mod tests {
         assert_eq!(dispatcher.cargo_balance[&'G'], -3);
     }
 
+    #[test]
+    fn reserved_slots_and_cargo_are_skipped() {
+        let mut dispatcher = Dispatcher::new();
+        let slots: Slots<char> = vec![
+            Slot::new(0.0, 0.0, Some(Payload::new('A')), None, SlotKind::CLASSIC),
+            Slot::new(0.0, 0.0, Some(Payload::new('A')), None, SlotKind::CLASSIC),
+            Slot::new(0.0, 0.0, None, Some(Payload::new('A')), SlotKind::CLASSIC),
+            Slot::new(0.0, 0.0, None, Some(Payload::new('A')), SlotKind::CLASSIC),
+        ]
+        .into_iter()
+        .collect();
+        dispatcher.calculate_cargo_balance(&slots, &Carriers::new());
+        let mut reserved = Reservations::new();
+
+        assert_eq!(
+            dispatcher.find_slot_with_mismatched_payload_and_free_target(&slots, &reserved),
+            Some((id(0), id(2)))
+        );
+        reserved.reserve(id(0));
+        reserved.reserve(id(2));
+        assert_eq!(
+            dispatcher.find_slot_with_mismatched_payload_and_free_target(&slots, &reserved),
+            Some((id(1), id(3)))
+        );
+        assert!(slots.values().all(|slot| !slot.taken_care_of));
+
+        // Cargo is balanced, until a task that changes the balance is planned
+        assert_eq!(dispatcher.find_missing_cargo(&slots, &reserved), None);
+        reserved.change_cargo_balance('A', -1);
+        assert_eq!(dispatcher.find_missing_cargo(&slots, &reserved), Some('A'));
+        assert!(dispatcher.cargo_balance.is_empty());
+    }
+
+    #[test]
+    fn slot_of_new_kind_is_found_after_the_index_is_built() {
+        let mut dispatcher = Dispatcher::new();
+        let mut slots: Slots<char> = (0..100)
+            .map(|i| Slot::new(f64::from(i) * 100.0, 0.0, None, None, SlotKind::CLASSIC))
+            .collect();
+        let pos = Position::new(0.0, 0.0);
+        assert_eq!(
+            dispatcher.find_closest_of_kind(&slots, &pos, SlotKind::PIT, |_| true),
+            None
+        );
+
+        let pit = slots.insert(Slot::new(5000.0, 100.0, None, None, SlotKind::PIT));
+        dispatcher.slot_added(pit, &slots[pit]);
+        assert_eq!(
+            dispatcher.find_closest_of_kind(&slots, &pos, SlotKind::PIT, |_| true),
+            Some(pit)
+        );
+    }
+
     #[test]
     fn find_closest_object1() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -743,7 +1409,9 @@ mod tests {
                 Some(Payload::new('C')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
             dispatcher
@@ -760,7 +1428,7 @@ mod tests {
     #[test]
     fn find_closest_object() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -770,24 +1438,26 @@ mod tests {
             ),
             make_slot_pit!(1000.0, 1000.0),
             make_slot_spawner!(1000.0, 1000.0),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
             dispatcher
                 .find_closest_object(&slots, &Position::new(10.0, 10.0), |slot| slot.is_pit()),
-            Some(1)
+            Some(id(1))
         );
         assert_eq!(
             dispatcher
                 .find_closest_object(&slots, &Position::new(10.0, 10.0), |slot| slot.is_spawner()),
-            Some(2)
+            Some(id(2))
         );
     }
 
     #[test]
     fn find_closest_object3() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -799,24 +1469,26 @@ mod tests {
             make_slot_pit!(0.0, 0.0),
             make_slot_spawner!(1000.0, 1000.0),
             make_slot_spawner!(0.0, 0.0),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
             dispatcher
                 .find_closest_object(&slots, &Position::new(10.0, 10.0), |slot| slot.is_pit()),
-            Some(2)
+            Some(id(2))
         );
         assert_eq!(
             dispatcher
                 .find_closest_object(&slots, &Position::new(10.0, 10.0), |slot| slot.is_spawner()),
-            Some(4)
+            Some(id(4))
         );
     }
 
     #[test]
     fn find_slot_with_payload_that_should_go_to_the_pit1() {
         let mut dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -859,23 +1531,27 @@ mod tests {
                 Some(Payload::new('G')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
-        dispatcher.calculate_cargo_balance(&slots);
+        dispatcher.calculate_cargo_balance(&slots, &Carriers::new());
         assert_ne!(
-            dispatcher.find_slot_with_payload_that_should_go_to_the_pit(&slots),
-            Some(2)
+            dispatcher
+                .find_slot_with_payload_that_should_go_to_the_pit(&slots, &Reservations::new()),
+            Some((id(2), 'E'))
         );
         assert_eq!(
-            dispatcher.find_slot_with_payload_that_should_go_to_the_pit(&slots),
-            Some(3)
+            dispatcher
+                .find_slot_with_payload_that_should_go_to_the_pit(&slots, &Reservations::new()),
+            Some((id(3), 'E'))
         );
     }
 
     #[test]
     fn find_slot_with_payload_that_should_go_to_the_pit2() {
         let mut dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -911,11 +1587,14 @@ mod tests {
                 Some(Payload::new('G')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
-        dispatcher.calculate_cargo_balance(&slots);
+        dispatcher.calculate_cargo_balance(&slots, &Carriers::new());
         assert_eq!(
-            dispatcher.find_slot_with_payload_that_should_go_to_the_pit(&slots),
+            dispatcher
+                .find_slot_with_payload_that_should_go_to_the_pit(&slots, &Reservations::new()),
             None
         );
     }
@@ -923,7 +1602,7 @@ mod tests {
     #[test]
     fn find_slot_that_contains() {
         let dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -945,26 +1624,28 @@ mod tests {
                 Some(Payload::new('B')),
                 SlotKind::CLASSIC,
             ),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         assert_eq!(
-            dispatcher.find_mismatched_slot_that_contains(&slots, 'A'),
-            Some(2)
+            dispatcher.find_mismatched_slot_that_contains(&slots, 'A', &Reservations::new()),
+            Some(id(2))
         );
         assert_eq!(
-            dispatcher.find_mismatched_slot_that_contains(&slots, 'X'),
-            Some(0)
+            dispatcher.find_mismatched_slot_that_contains(&slots, 'X', &Reservations::new()),
+            Some(id(0))
         );
         assert_eq!(
-            dispatcher.find_mismatched_slot_that_contains(&slots, 'Y'),
+            dispatcher.find_mismatched_slot_that_contains(&slots, 'Y', &Reservations::new()),
             None
         );
     }
 
     #[test]
-    fn calculate_slot_distances() {
+    fn distance_slot_slot() {
         let mut dispatcher = Dispatcher::new();
-        let slots = vec![
+        let slots: Slots<char> = vec![
             Slot::new(
                 100.0,
                 100.0,
@@ -974,25 +1655,31 @@ mod tests {
             ),
             Slot::new(100.0, 100.0, None, None, SlotKind::CLASSIC),
             Slot::new(200.0, 200.0, None, None, SlotKind::CLASSIC),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
-        dispatcher.calculate_slot_distances(&slots);
-
-        approx::assert_abs_diff_eq!(dispatcher.get_distance_slot_slot(0, 1), 0.0);
+        approx::assert_abs_diff_eq!(dispatcher.get_distance_slot_slot(&slots, id(0), id(1)), 0.0);
         approx::assert_abs_diff_eq!(
-            dispatcher.get_distance_slot_slot(1, 2),
-            100.0 * (2.0 as f64).sqrt()
+            dispatcher.get_distance_slot_slot(&slots, id(1), id(2)),
+            100.0 * 2.0_f64.sqrt()
         );
         approx::assert_abs_diff_eq!(
-            dispatcher.get_distance_slot_slot(2, 1),
-            dispatcher.get_distance_slot_slot(1, 2)
-        )
+            dispatcher.get_distance_slot_slot(&slots, id(2), id(1)),
+            dispatcher.get_distance_slot_slot(&slots, id(1), id(2))
+        );
+
+        // Wall between the slots, with the passage at the bottom
+        let mut obstacles = ObstacleGrid::new(0.0, 0.0, 10.0, 30, 30);
+        obstacles.block_rect(140.0, 0.0, 159.0, 249.0);
+        dispatcher.set_obstacles(Some(obstacles));
+        assert!(dispatcher.get_distance_slot_slot(&slots, id(1), id(2)) > 200.0);
     }
 
     #[test]
     fn find_any_temporary_slot() {
-        let mut dispatcher = Dispatcher::new();
-        let slots = vec![
+        let dispatcher = Dispatcher::new();
+        let slots: Slots<char> = vec![
             Slot::new(
                 200.0,
                 210.0,
@@ -1010,19 +1697,20 @@ mod tests {
             Slot::new(600.0, 550.0, None, None, SlotKind::CLASSIC),
             Slot::new(500.0, 450.0, None, None, SlotKind::CLASSIC),
             Slot::new(300.0, 350.0, None, None, SlotKind::CLASSIC),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
-        dispatcher.calculate_slot_distances(&slots);
         let tmp_slot = dispatcher
             ._find_any_temporary_slot(&slots, Payload::new('A'))
             .unwrap();
-        assert_eq!(tmp_slot, 2);
+        assert_eq!(tmp_slot, id(2));
     }
 
     #[test]
     fn find_closest_temporary_slot() {
-        let mut dispatcher = Dispatcher::new();
-        let slots = vec![
+        let dispatcher = Dispatcher::new();
+        let slots: Slots<char> = vec![
             Slot::new(
                 200.0,
                 210.0,
@@ -1040,15 +1728,36 @@ mod tests {
             Slot::new(600.0, 550.0, None, None, SlotKind::CLASSIC),
             Slot::new(500.0, 450.0, None, None, SlotKind::CLASSIC),
             Slot::new(300.0, 350.0, None, None, SlotKind::CLASSIC),
-        ];
+        ]
+        .into_iter()
+        .collect();
 
         let mut payload = Payload::new('A');
-        payload.taken_from = Some(1);
+        payload.taken_from = Some(id(1));
+
+        let tmp_slot = dispatcher
+            .find_closest_temporary_slot(&slots, payload, &Position::new(0.0, 0.0))
+            .unwrap();
+        assert_eq!(tmp_slot, id(4));
+    }
+
+    #[test]
+    fn find_closest_temporary_slot_without_source() {
+        let dispatcher = Dispatcher::new();
+        let slots: Slots<char> = vec![
+            Slot::new(600.0, 550.0, None, None, SlotKind::CLASSIC),
+            Slot::new(500.0, 450.0, None, None, SlotKind::CLASSIC),
+            Slot::new(300.0, 350.0, None, None, SlotKind::CLASSIC),
+        ]
+        .into_iter()
+        .collect();
+
+        // Source slot is gone, so the distance is measured from the carrier
+        let payload = Payload::new('A');
 
-        dispatcher.calculate_slot_distances(&slots);
         let tmp_slot = dispatcher
-            .find_closest_temporary_slot(&slots, payload)
+            .find_closest_temporary_slot(&slots, payload, &Position::new(520.0, 470.0))
             .unwrap();
-        assert_eq!(tmp_slot, 4);
+        assert_eq!(tmp_slot, id(1));
     }
 }
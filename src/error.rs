@@ -0,0 +1,114 @@
+use super::arena::*;
+
+use std::error::Error;
+use std::fmt;
+
+/// Errors that can be reported by the swarm while ticking.
+///
+/// These are the invariant violations that used to abort the whole process.
+/// They usually mean that slots were modified through `get_slots_mut()` in a way
+/// that is not consistent with what the carriers are currently doing.
+///
+/// Variant            | Meaning
+/// -------------------|--------
+/// PickupFromPit      | Carrier wanted to pick up the payload from the pit
+/// PickupFromEmpty    | Carrier wanted to pick up the payload from slot that has no payload
+/// DropIntoSpawner    | Carrier wanted to put the payload down into the spawner
+/// EmptyCarrierToPit  | Carrier without payload was sent to the pit
+/// NoPayload          | Carrier was looking for a target for the payload, but carries nothing
+/// StaleSlot          | Carrier refers to the slot that no longer exists
+/// InvalidAssignment  | Dispatch strategy gave the task to busy carrier or the task itself cannot be performed
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SwarmError {
+    PickupFromPit { carrier: CarrierId, slot: SlotId },
+    PickupFromEmpty { carrier: CarrierId, slot: SlotId },
+    DropIntoSpawner { carrier: CarrierId, slot: SlotId },
+    EmptyCarrierToPit { carrier: CarrierId, slot: SlotId },
+    NoPayload { carrier: CarrierId },
+    StaleSlot { carrier: CarrierId, slot: SlotId },
+    InvalidAssignment { carrier: CarrierId },
+}
+
+impl fmt::Display for SwarmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SwarmError::PickupFromPit { carrier, slot } => write!(
+                f,
+                "carrier {} is trying to pick up from the pit (slot {})",
+                carrier, slot
+            ),
+            SwarmError::PickupFromEmpty { carrier, slot } => write!(
+                f,
+                "carrier {} wants to pick up from slot {} without payload",
+                carrier, slot
+            ),
+            SwarmError::DropIntoSpawner { carrier, slot } => write!(
+                f,
+                "carrier {} is trying to drop into the spawner (slot {})",
+                carrier, slot
+            ),
+            SwarmError::EmptyCarrierToPit { carrier, slot } => write!(
+                f,
+                "carrier {} is going empty to the pit (slot {})",
+                carrier, slot
+            ),
+            SwarmError::NoPayload { carrier } => write!(
+                f,
+                "carrier {} is looking for a target, but carries no payload",
+                carrier
+            ),
+            SwarmError::StaleSlot { carrier, slot } => write!(
+                f,
+                "carrier {} refers to slot {} which does not exist",
+                carrier, slot
+            ),
+            SwarmError::InvalidAssignment { carrier } => write!(
+                f,
+                "dispatch strategy gave carrier {} a task that cannot be performed",
+                carrier
+            ),
+        }
+    }
+}
+
+impl Error for SwarmError {}
+
+/// Result of a single, successful tick of the swarm
+///
+/// Variant    | Meaning
+/// -----------|--------
+/// InProgress | Carriers are still working on the layout
+/// Finished   | Target layout is reached, there is no more action required
+/// Stalled    | Target layout can't be reached without changing the slots or carriers, see `Swarm::progress()`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TickOutcome {
+    InProgress,
+    Finished,
+    Stalled,
+}
+
+impl TickOutcome {
+    /// Returns `true` if carriers have finished transferring the layout
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// assert!(swarm_it::TickOutcome::Finished.is_finished());
+    /// assert!(!swarm_it::TickOutcome::InProgress.is_finished());
+    /// ```
+    pub fn is_finished(self) -> bool {
+        self == TickOutcome::Finished
+    }
+
+    /// Returns `true` if carriers can't make any more progress toward the target layout
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// assert!(swarm_it::TickOutcome::Stalled.is_stalled());
+    /// assert!(!swarm_it::TickOutcome::Finished.is_stalled());
+    /// ```
+    pub fn is_stalled(self) -> bool {
+        self == TickOutcome::Stalled
+    }
+}
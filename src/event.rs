@@ -0,0 +1,67 @@
+use super::arena::*;
+use super::carrier::*;
+
+/// Things that happened in the swarm during the tick. Events are collected
+/// by the `Swarm` and can be retrieved with `Swarm::drain_events()`.
+///
+/// Event          | Meaning
+/// ---------------|--------
+/// PickedUp       | Carrier picked up the `cargo` from the `slot`
+/// PutDown        | Carrier put the `cargo` down into the `slot` that was its final target
+/// DroppedIntoPit | Carrier dropped the `cargo` into the pit
+/// Spawned        | Carrier received the `cargo` from the spawner
+/// StateChanged   | Carrier switched from one state to another
+/// TemporaryDrop  | Carrier had no target for the `cargo` and put it down into the temporary `slot`
+/// Rerouted       | Carrier that was heading to the temporary slot found the proper target for the payload
+/// Unreachable    | Carrier couldn't find the way around the obstacles to the `slot`, which is skipped from now on
+/// Stalled        | Swarm made no progress toward the target layout for `seconds` of simulated time, see `Swarm::get_stall_report()`
+/// DeadlineMissed | Deadline of the `slot` has passed, but the slot doesn't hold its target payloads
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event<T> {
+    PickedUp {
+        carrier: CarrierId,
+        slot: SlotId,
+        cargo: T,
+    },
+    PutDown {
+        carrier: CarrierId,
+        slot: SlotId,
+        cargo: T,
+    },
+    DroppedIntoPit {
+        carrier: CarrierId,
+        slot: SlotId,
+        cargo: T,
+    },
+    Spawned {
+        carrier: CarrierId,
+        slot: SlotId,
+        cargo: T,
+    },
+    StateChanged {
+        carrier: CarrierId,
+        from: State,
+        to: State,
+    },
+    TemporaryDrop {
+        carrier: CarrierId,
+        slot: SlotId,
+        cargo: T,
+    },
+    Rerouted {
+        carrier: CarrierId,
+        from: SlotId,
+        to: SlotId,
+    },
+    Unreachable {
+        carrier: CarrierId,
+        slot: SlotId,
+    },
+    Stalled {
+        seconds: f64,
+    },
+    DeadlineMissed {
+        slot: SlotId,
+    },
+}
@@ -0,0 +1,399 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::carrier::*;
+use super::slot::*;
+use super::Swarm;
+
+/// Layout of the swarm that can be stored in a JSON file: the slots and the carriers
+/// with their parameters. Only the position is required, omitted parameters take
+/// their default values.
+///
+/// Field             | Meaning
+/// ------------------|--------
+/// slots             | List of slots
+/// slots.x, slots.y  | Position of the slot
+/// slots.kind        | `"CLASSIC"` (default), `"PIT"` or `"SPAWNER"`
+/// slots.capacity    | How many payloads the slot holds, 1 by default (see `Slot::new_bin()`)
+/// slots.current     | Cargo currently in the slot
+/// slots.target      | Cargo the slot should hold
+/// slots.priority, slots.deadline | Urgency of the slot, see `Slot::set_priority()` and `Slot::set_deadline()`
+/// carriers          | List of carriers
+/// carriers.x, carriers.y | Position of the carrier
+/// carriers.acceleration, carriers.deceleration, carriers.max_speed, carriers.turn_rate, carriers.radius, carriers.capacity, carriers.turn_while_moving | Parameters, see the setters of the `Carrier`
+///
+/// # Example
+///
+/// ```
+/// use swarm_it::*;
+/// let layout: Layout<char> = serde_json::from_str(r#"{
+///     "slots": [
+///         { "x": 100.0, "y": 100.0, "current": ["A"], "target": ["B"] },
+///         { "x": 200.0, "y": 100.0, "capacity": 3, "current": ["B", "A"], "target": ["A"] },
+///         { "x": 300.0, "y": 300.0, "kind": "PIT" }
+///     ],
+///     "carriers": [
+///         { "x": 0.0, "y": 0.0, "max_speed": 8.0, "capacity": 2 }
+///     ]
+/// }"#).unwrap();
+///
+/// let mut game = Swarm::<char>::new();
+/// game.add_layout(&layout).unwrap();
+/// assert_eq!(game.get_slots().len(), 3);
+/// ```
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Layout<T> {
+    #[serde(default = "Vec::new")]
+    pub slots: Vec<SlotLayout<T>>,
+    #[serde(default)]
+    pub carriers: Vec<CarrierLayout>,
+}
+
+impl<T> Default for Layout<T> {
+    fn default() -> Self {
+        Layout {
+            slots: Vec::new(),
+            carriers: Vec::new(),
+        }
+    }
+}
+
+/// Slot stored in the `Layout`
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SlotLayout<T> {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default = "default_kind")]
+    pub kind: SlotKind,
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    #[serde(default = "Vec::new")]
+    pub current: Vec<T>,
+    #[serde(default = "Vec::new")]
+    pub target: Vec<T>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<f64>,
+}
+
+/// Carrier stored in the `Layout`
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct CarrierLayout {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acceleration: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deceleration: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_speed: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_while_moving: Option<bool>,
+}
+
+fn default_kind() -> SlotKind {
+    SlotKind::CLASSIC
+}
+
+fn default_capacity() -> usize {
+    1
+}
+
+fn is_zero(priority: &i32) -> bool {
+    *priority == 0
+}
+
+// Motion parameters that are given must be positive numbers
+fn is_positive(parameter: Option<f64>) -> bool {
+    match parameter {
+        Some(value) => value > 0.0 && value.is_finite(),
+        None => true,
+    }
+}
+
+/// Errors that can be reported while loading or saving the layout
+///
+/// Variant        | Meaning
+/// ---------------|--------
+/// Io             | File couldn't be read or written
+/// Format         | File is not a valid layout
+/// InvalidSlot    | Slot with given index can't be created, e.g. its payloads do not fit or a pit has payloads
+/// InvalidCarrier | Carrier with given index can't be created, e.g. its capacity is zero or any of its motion parameters is not positive
+#[derive(Debug)]
+pub enum LayoutError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    InvalidSlot { index: usize },
+    InvalidCarrier { index: usize },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::Io(e) => write!(f, "layout file couldn't be accessed: {}", e),
+            LayoutError::Format(e) => write!(f, "layout file is malformed: {}", e),
+            LayoutError::InvalidSlot { index } => {
+                write!(f, "slot {} of the layout is invalid", index)
+            }
+            LayoutError::InvalidCarrier { index } => {
+                write!(f, "carrier {} of the layout is invalid", index)
+            }
+        }
+    }
+}
+
+impl Error for LayoutError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LayoutError::Io(e) => Some(e),
+            LayoutError::Format(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LayoutError {
+    fn from(e: std::io::Error) -> Self {
+        LayoutError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for LayoutError {
+    fn from(e: serde_json::Error) -> Self {
+        LayoutError::Format(e)
+    }
+}
+
+impl<T: DeserializeOwned> Layout<T> {
+    /// Reads the layout from the JSON file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let path = std::env::temp_dir().join("swarm_it_layout_from_file.json");
+    /// std::fs::write(&path, r#"{ "carriers": [{ "x": 10.0, "y": 20.0 }] }"#).unwrap();
+    ///
+    /// let layout = Layout::<char>::from_file(&path).unwrap();
+    /// assert_eq!(layout.carriers.len(), 1);
+    /// ```
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Layout<T>, LayoutError> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+impl<T> SlotLayout<T> {
+    fn is_valid(&self) -> bool {
+        let fits = self.capacity > 0
+            && self.current.len() <= self.capacity
+            && self.target.len() <= self.capacity;
+        match self.kind {
+            SlotKind::CLASSIC => fits,
+            // Pits and spawners hold nothing
+            SlotKind::PIT | SlotKind::SPAWNER => self.current.is_empty() && self.target.is_empty(),
+        }
+    }
+}
+
+impl<T: PartialEq + Eq + Hash + Copy> SlotLayout<T> {
+    fn to_slot(&self) -> Slot<T> {
+        let mut slot = match self.kind {
+            SlotKind::CLASSIC => Slot::new_bin(
+                self.x,
+                self.y,
+                self.capacity,
+                self.current.clone(),
+                self.target.clone(),
+            ),
+            kind => Slot::new(self.x, self.y, None, None, kind),
+        };
+        slot.set_priority(self.priority);
+        slot.set_deadline(self.deadline);
+        slot
+    }
+
+    fn from_slot(slot: &Slot<T>) -> SlotLayout<T> {
+        SlotLayout {
+            x: slot.get_position().x,
+            y: slot.get_position().y,
+            kind: slot.get_kind(),
+            capacity: slot.get_capacity(),
+            current: slot.get_contents().iter().map(|p| p.cargo).collect(),
+            target: slot.get_targets().to_vec(),
+            priority: slot.get_priority(),
+            deadline: slot.get_deadline(),
+        }
+    }
+}
+
+impl CarrierLayout {
+    fn is_valid(&self) -> bool {
+        self.capacity != Some(0)
+            && [
+                self.acceleration,
+                self.deceleration,
+                self.max_speed,
+                self.turn_rate,
+            ]
+            .iter()
+            .all(|&parameter| is_positive(parameter))
+    }
+
+    fn to_carrier<T: PartialEq + Eq + Hash + Copy>(&self) -> Carrier<T> {
+        let mut carrier = Carrier::new(self.x, self.y);
+        if let Some(acceleration) = self.acceleration {
+            carrier.set_acceleration(acceleration);
+        }
+        if let Some(deceleration) = self.deceleration {
+            carrier.set_deceleration(deceleration);
+        }
+        if let Some(max_speed) = self.max_speed {
+            carrier.set_max_speed(max_speed);
+        }
+        if let Some(turn_rate) = self.turn_rate {
+            carrier.set_turn_rate(turn_rate);
+        }
+        if let Some(radius) = self.radius {
+            carrier.set_radius(radius);
+        }
+        if let Some(capacity) = self.capacity {
+            carrier.set_capacity(capacity);
+        }
+        if let Some(turn_while_moving) = self.turn_while_moving {
+            carrier.set_turn_while_moving(turn_while_moving);
+        }
+        carrier
+    }
+
+    fn from_carrier<T: PartialEq + Eq + Hash + Copy>(carrier: &Carrier<T>) -> CarrierLayout {
+        CarrierLayout {
+            x: carrier.get_position().x,
+            y: carrier.get_position().y,
+            acceleration: Some(carrier.get_acceleration()),
+            deceleration: Some(carrier.get_deceleration()),
+            max_speed: Some(carrier.get_max_speed()),
+            turn_rate: Some(carrier.get_turn_rate()),
+            radius: Some(carrier.get_radius()),
+            capacity: Some(carrier.get_capacity()),
+            turn_while_moving: Some(carrier.get_turn_while_moving()),
+        }
+    }
+}
+
+impl<T: PartialEq + Eq + Hash + Copy + Serialize + DeserializeOwned> Swarm<T> {
+    /// Constructs a new `Swarm` with the slots and carriers read from the JSON layout file.
+    /// See `Layout` for the description of the format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let path = std::env::temp_dir().join("swarm_it_from_layout_file.json");
+    /// std::fs::write(&path, r#"{ "slots": [{ "x": 0.0, "y": 0.0, "current": ["X"] }] }"#).unwrap();
+    ///
+    /// let game = Swarm::<char>::from_layout_file(&path).unwrap();
+    /// assert_eq!(game.get_slots().len(), 1);
+    /// ```
+    pub fn from_layout_file<P: AsRef<Path>>(path: P) -> Result<Swarm<T>, LayoutError> {
+        let layout = Layout::from_file(path)?;
+        let mut game = Swarm::new();
+        game.add_layout(&layout)?;
+        Ok(game)
+    }
+
+    /// Writes the slots and carriers to the JSON layout file.
+    /// See `to_layout()` for what is stored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_slot(Slot::new(100.0, 100.0, Some(Payload::new('X')), None, SlotKind::CLASSIC));
+    /// game.add_carrier(Carrier::new(0.0, 0.0));
+    ///
+    /// let path = std::env::temp_dir().join("swarm_it_save_layout.json");
+    /// game.save_layout(&path).unwrap();
+    /// let loaded = Swarm::<char>::from_layout_file(&path).unwrap();
+    /// assert_eq!(loaded.to_layout(), game.to_layout());
+    /// ```
+    pub fn save_layout<P: AsRef<Path>>(&self, path: P) -> Result<(), LayoutError> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &self.to_layout())?;
+        Ok(())
+    }
+}
+
+impl<T: PartialEq + Eq + Hash + Copy> Swarm<T> {
+    /// Adds the slots and carriers of the layout. Nothing is added if
+    /// any of the slots or carriers is invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut layout = Layout::default();
+    /// layout.carriers.push(CarrierLayout { x: 10.0, y: 20.0, ..Default::default() });
+    ///
+    /// let mut game = Swarm::<char>::with_seed(2019);
+    /// game.add_layout(&layout).unwrap();
+    /// assert_eq!(game.get_carriers().len(), 1);
+    /// ```
+    pub fn add_layout(&mut self, layout: &Layout<T>) -> Result<(), LayoutError> {
+        if let Some(index) = layout.slots.iter().position(|slot| !slot.is_valid()) {
+            return Err(LayoutError::InvalidSlot { index });
+        }
+        if let Some(index) = layout
+            .carriers
+            .iter()
+            .position(|carrier| !carrier.is_valid())
+        {
+            return Err(LayoutError::InvalidCarrier { index });
+        }
+        layout.slots.iter().for_each(|slot| {
+            self.add_slot(slot.to_slot());
+        });
+        layout.carriers.iter().for_each(|carrier| {
+            self.add_carrier(carrier.to_carrier());
+        });
+        Ok(())
+    }
+
+    /// Returns the current layout of the swarm: slots with their current and target cargo,
+    /// and carriers at their current positions. Payloads carried at the moment are
+    /// not part of the layout, use the `serde` feature to store the complete state of the swarm.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_slot(Slot::new_bin(100.0, 100.0, 2, vec!['X'], vec!['Y', 'Y']));
+    /// let layout = game.to_layout();
+    /// assert_eq!(layout.slots[0].capacity, 2);
+    /// assert_eq!(layout.slots[0].target, vec!['Y', 'Y']);
+    /// ```
+    pub fn to_layout(&self) -> Layout<T> {
+        Layout {
+            slots: self.slots.values().map(SlotLayout::from_slot).collect(),
+            carriers: self
+                .carriers
+                .values()
+                .map(CarrierLayout::from_carrier)
+                .collect(),
+        }
+    }
+}
@@ -1,36 +1,74 @@
 #[macro_use]
 mod macros;
 
+mod arena;
+mod assignment;
+mod avoidance;
 mod carrier;
 mod dispatcher;
+mod error;
+mod event;
+#[cfg(feature = "layout")]
+mod layout;
+mod navigation;
 mod payload;
 mod position;
+mod progress;
+#[cfg(feature = "render")]
+mod render;
+#[cfg(feature = "replay")]
+mod replay;
 mod slot;
+mod spatial;
+mod stall;
+mod strategy;
+mod text;
 mod tools;
+mod validation;
 
 use std::hash::Hash;
 
+use rand::{Rng, SeedableRng};
+
+pub use arena::*;
+use avoidance::*;
 pub use carrier::*;
 use dispatcher::*;
+pub use error::*;
+pub use event::*;
+#[cfg(feature = "layout")]
+pub use layout::*;
+pub use navigation::*;
 pub use payload::*;
+pub use position::*;
+pub use progress::*;
+#[cfg(feature = "render")]
+pub use render::*;
+#[cfg(feature = "replay")]
+pub use replay::*;
 pub use slot::*;
+pub use stall::*;
+pub use strategy::*;
+pub use text::*;
+use tools::*;
+pub use validation::*;
 
 #[macro_use]
 extern crate approx;
 
-fn _debug_dump_slots(slots: &[Slot<char>]) {
-    for (i, v) in slots.iter().enumerate() {
+// Tolerance used when deciding whether accumulated time is enough for another step
+pub(crate) const TIME_EPSILON: f64 = 1e-9;
+
+fn _debug_dump_slots(slots: &Slots<char>) {
+    for (i, v) in slots.iter() {
         print!("Slot [{}]: ", i);
 
-        match slots[i].current_payload {
-            Some(p) => print!("{} ", p.cargo),
-            None => print!("None "),
+        for p in v.get_contents() {
+            print!("{} ", p.cargo);
         }
-
-        let [_, target] = v.get_payloads();
-        match target {
-            Some(p) => print!("    {} ", p.cargo),
-            None => print!("    None "),
+        print!("   ");
+        for cargo in v.get_targets() {
+            print!(" {}", cargo);
         }
 
         print!("\tTaken care of={}", v.taken_care_of);
@@ -40,30 +78,51 @@ fn _debug_dump_slots(slots: &[Slot<char>]) {
 }
 
 fn _debug_dump_slot_distances<T: PartialEq + Eq + Hash + Copy>(
-    slots: &[Slot<T>],
+    slots: &Slots<T>,
     dispatcher: &Dispatcher<T>,
 ) {
-    slots.iter().enumerate().for_each(|(i1, _)| {
-        slots.iter().enumerate().for_each(|(i2, _)| {
+    slots.ids().for_each(|i1| {
+        slots.ids().for_each(|i2| {
             println!(
                 "{}->{} = {}",
                 i1,
                 i2,
-                dispatcher.get_distance_slot_slot(i1, i2)
+                dispatcher.get_distance_slot_slot(slots, i1, i2)
             );
         })
     });
 }
 
 /// Main struct that allow you to talk to the library
-#[derive(Default)]
+///
+/// With the `serde` feature enabled the whole state of the swarm can be serialized
+/// and restored later, so the simulation continues exactly where it left off.
+/// The `DispatchStrategy` is not serialized, restored swarm uses the `GreedyStrategy`
+/// until another one is set with `set_strategy()`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Swarm<T: PartialEq + Eq + Hash + Copy> {
-    carriers: Vec<Carrier<T>>,
-    slots: Vec<Slot<T>>,
-    first_tick: bool,
-    idle_ticks: u8,
+    carriers: Carriers<T>,
+    slots: Slots<T>,
+    slots_dirty: bool,
+    // Every slot holds what it should and no carrier carries anything
+    complete: bool,
     tick_counter: u64,
+    // Simulation time, in seconds, and the time at which the deadlines of the slots
+    // are to be checked again
+    time: f64,
+    deadline_check: Option<f64>,
     dispatcher: Dispatcher<T>,
+    events: Vec<Event<T>>,
+    time_step: f64,
+    pending_time: f64,
+    rng: SwarmRng,
+    stall: StallDetector,
+}
+
+impl<T: PartialEq + Eq + Hash + Copy> Default for Swarm<T> {
+    fn default() -> Self {
+        Swarm::new()
+    }
 }
 
 impl<T: PartialEq + Eq + Hash + Copy> Swarm<T> {
@@ -77,18 +136,52 @@ impl<T: PartialEq + Eq + Hash + Copy> Swarm<T> {
     /// let game = swarm_it::Swarm::<char>::new();
     /// ```
     pub fn new() -> Swarm<T> {
+        Swarm::with_seed(rand::thread_rng().gen())
+    }
+
+    /// Constructs a new `Swarm` that uses the random number generator initialized with the `seed`.
+    /// Swarms with the same seed, layout and carriers behave identically, which
+    /// is useful for tests and replays. `Swarm::new()` picks a random seed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let game = swarm_it::Swarm::<char>::with_seed(2019);
+    /// ```
+    pub fn with_seed(seed: u64) -> Swarm<T> {
         Swarm {
-            carriers: Vec::new(),
-            slots: Vec::new(),
-            first_tick: true,
-            idle_ticks: 0,
+            carriers: Arena::new(),
+            slots: Arena::new(),
+            slots_dirty: false,
+            complete: false,
             tick_counter: 0,
+            time: 0.0,
+            deadline_check: None,
             dispatcher: Dispatcher::new(),
+            events: Vec::new(),
+            time_step: 1.0,
+            pending_time: 0.0,
+            rng: SwarmRng::seed_from_u64(seed),
+            stall: StallDetector::default(),
         }
     }
 
+    /// Constructs a new `Swarm` that uses custom strategy for handing out tasks to idle carriers.
+    /// `Swarm::new()` uses the `GreedyStrategy`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let game = swarm_it::Swarm::<char>::with_strategy(swarm_it::OptimalStrategy);
+    /// ```
+    pub fn with_strategy<S: DispatchStrategy<T> + 'static>(strategy: S) -> Swarm<T> {
+        let mut swarm = Swarm::new();
+        swarm.set_strategy(strategy);
+        swarm
+    }
+
     /// Adds new carrier.
-    /// Returns the index of the new carrier.
+    /// Returns the handle of the new carrier.
     ///
     /// # Example
     ///
@@ -97,12 +190,14 @@ impl<T: PartialEq + Eq + Hash + Copy> Swarm<T> {
     /// let mut game = Swarm::<char>::new();
     /// game.add_carrier(Carrier::new(100.0, 200.0));
     /// ```
-    pub fn add_carrier(&mut self, carrier: Carrier<T>) -> usize {
-        Swarm::<T>::add_object(&mut self.carriers, carrier)
+    pub fn add_carrier(&mut self, mut carrier: Carrier<T>) -> CarrierId {
+        carrier.idle_rotation_direction = Carrier::<T>::pick_random_idle_rotation(&mut self.rng);
+        self.stall.reset();
+        self.carriers.insert(carrier)
     }
 
     /// Adds new slot.
-    /// Returns the index of the new slot.
+    /// Returns the handle of the new slot.
     ///
     /// # Example
     ///
@@ -111,66 +206,481 @@ impl<T: PartialEq + Eq + Hash + Copy> Swarm<T> {
     /// let mut game = Swarm::<char>::new();
     /// game.add_slot(Slot::new(100.0, 100.0, None, Some(Payload::new('X')), swarm_it::SlotKind::CLASSIC));
     /// ```
-    pub fn add_slot(&mut self, slot: Slot<T>) -> usize {
-        Swarm::<T>::add_object(&mut self.slots, slot)
+    pub fn add_slot(&mut self, slot: Slot<T>) -> SlotId {
+        self.dispatcher.change_slot_balance(&slot, 1);
+        self.stall.reset();
+        if slot.get_deadline().is_some() {
+            self.deadlines_changed();
+        }
+        let id = self.slots.insert(slot);
+        self.dispatcher.slot_added(id, &self.slots[id]);
+        id
+    }
+
+    /// Removes the carrier and returns it together with the payload it was carrying.
+    /// Returns `None` if the handle is stale.
+    ///
+    /// Slots that the carrier was heading to are released, so other
+    /// carriers can take care of them. Payload that leaves with the carrier
+    /// is considered missing and will be brought again from the spawner, if any.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// let id = game.add_carrier(Carrier::new(100.0, 200.0));
+    /// let carrier = game.remove_carrier(id).unwrap();
+    /// assert_eq!(carrier.get_payload(), None);
+    /// assert!(game.remove_carrier(id).is_none());
+    /// ```
+    pub fn remove_carrier(&mut self, id: CarrierId) -> Option<Carrier<T>> {
+        let carrier = self.carriers.remove(id)?;
+        self.stall.reset();
+        self.dispatcher.carrier_removed(&carrier, &mut self.slots);
+        Some(carrier)
+    }
+
+    /// Removes the slot and returns it. Returns `None` if the handle is stale.
+    ///
+    /// Carriers that were going to pick up from the slot become idle and get new tasks.
+    /// Carriers that were bringing payload to the slot look for another target.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// let id = game.add_slot(Slot::new(100.0, 100.0, None, Some(Payload::new('X')), SlotKind::CLASSIC));
+    /// assert!(game.remove_slot(id).is_some());
+    /// assert!(game.get_slots().is_empty());
+    /// ```
+    pub fn remove_slot(&mut self, id: SlotId) -> Option<Slot<T>> {
+        if !self.slots.contains(id) {
+            return None;
+        }
+        self.stall.reset();
+        let states = self.carrier_states();
+        self.dispatcher
+            .slot_removed(id, &mut self.carriers, &mut self.slots);
+        self.record_state_changes(&states);
+        self.slots.remove(id)
     }
 
     /// Returns all carriers
-    pub fn get_carriers(&self) -> &Vec<Carrier<T>> {
+    pub fn get_carriers(&self) -> &Arena<CarrierId, Carrier<T>> {
         &self.carriers
     }
 
-    /// Returns all carriers
-    pub fn get_carriers_mut(&mut self) -> &mut Vec<Carrier<T>> {
+    /// Returns all carriers. Use `remove_carrier()` instead of removing them directly,
+    /// otherwise the slots they were heading to will never be released.
+    pub fn get_carriers_mut(&mut self) -> &mut Arena<CarrierId, Carrier<T>> {
         &mut self.carriers
     }
 
     /// Returns all slots
-    pub fn get_slots(&self) -> &Vec<Slot<T>> {
+    pub fn get_slots(&self) -> &Arena<SlotId, Slot<T>> {
         &self.slots
     }
 
-    /// Returns all slots
-    pub fn get_slots_mut(&mut self) -> &mut Vec<Slot<T>> {
+    /// Returns all slots. Use `remove_slot()` instead of removing them directly,
+    /// otherwise carriers will end up with stale handles.
+    ///
+    /// The swarm can't tell what was changed, so the cargo is counted again from scratch
+    /// and the slot positions are indexed again before the next tick. Use `update_slot()` to change a single slot.
+    pub fn get_slots_mut(&mut self) -> &mut Arena<SlotId, Slot<T>> {
+        self.slots_dirty = true;
+        self.stall.reset();
+        self.deadlines_changed();
+        self.dispatcher.slots_changed();
         &mut self.slots
     }
 
+    /// Changes the slot with the function `f` and returns its result.
+    /// Returns `None` if the handle is stale.
+    ///
+    /// Only the cargo of this slot is counted again, so it is the cheap way
+    /// of changing the payloads of slots in a large layout.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// let id = game.add_slot(Slot::new(100.0, 100.0, None, None, SlotKind::CLASSIC));
+    /// game.update_slot(id, |slot| slot.set_target_payload(Some(Payload::new('X'))));
+    /// assert_eq!(game.get_slots()[id].get_payloads()[1], Some(Payload::new('X')));
+    /// ```
+    pub fn update_slot<R, F: FnOnce(&mut Slot<T>) -> R>(&mut self, id: SlotId, f: F) -> Option<R> {
+        let slot = self.slots.get_mut(id)?;
+        self.stall.reset();
+        self.dispatcher.change_slot_balance(slot, -1);
+        let result = f(slot);
+        self.dispatcher.change_slot_balance(slot, 1);
+        self.deadlines_changed();
+        Some(result)
+    }
+
+    /// Sets the strategy that decides which idle carrier should perform which task.
+    /// See `DispatchStrategy` for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.set_strategy(OptimalStrategy);
+    /// ```
+    pub fn set_strategy<S: DispatchStrategy<T> + 'static>(&mut self, strategy: S) {
+        self.dispatcher.strategy = Box::new(strategy);
+    }
+
     /// The engine must be regularly ticked by the outside world by invoking this function.
     /// At each tick swarm will perform calculation of the internal state logic, move
     /// carriers around, etc.
     ///
-    /// Returns `true` if there were no more action required, meaning that carriers have finished
-    /// tranferring the layout to target position.
+    /// Each tick advances the simulation by one second, same as `tick_dt(1.0)`.
+    ///
+    /// Returns `TickOutcome::Finished` if carriers have finished tranferring the layout to target position:
+    /// every slot holds its target payloads and no carrier carries anything.
+    /// Returns `TickOutcome::Stalled` if the target layout can't be reached, because it fails
+    /// the `validate()` or carriers made no progress for too long, see `progress()`.
+    ///
+    /// Returns `SwarmError` if carriers ended up in an inconsistent state, for example
+    /// when slot was modified through `get_slots_mut()` while carrier was heading to it.
+    /// The tick is aborted at the first error, so some carriers may already have been updated.
+    ///
+    /// Things that happened during the tick are available through `drain_events()`.
     ///
     /// # Example
     ///
     /// ```
     /// use swarm_it::*;
     /// let mut game = Swarm::<char>::new();
-    /// if game.tick() { println!("Job finished, yay!"); };
+    /// match game.tick() {
+    ///     Ok(outcome) if outcome.is_finished() => println!("Job finished, yay!"),
+    ///     Ok(_) => (),
+    ///     Err(e) => println!("Oops: {}", e),
+    /// };
+    /// ```
+    pub fn tick(&mut self) -> Result<TickOutcome, SwarmError> {
+        self.tick_dt(1.0)
+    }
+
+    /// Advances the simulation by `dt` seconds. Carrier speed is expressed in units per second,
+    /// acceleration in units per second² and turning in radians per second.
+    ///
+    /// Time is consumed in fixed steps (see `set_time_step()`) and the remainder is carried
+    /// over to the next call, so the result doesn't depend on the frame rate of the host.
+    /// If `dt` is shorter than the step, nothing moves until enough time is accumulated.
+    ///
+    /// Returns the same as `tick()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.set_time_step(1.0 / 60.0);
+    /// // Host is running at 30 FPS, so two steps are made each frame
+    /// game.tick_dt(1.0 / 30.0).unwrap();
+    /// ```
+    pub fn tick_dt(&mut self, dt: f64) -> Result<TickOutcome, SwarmError> {
+        self.pending_time += dt;
+        while self.pending_time + TIME_EPSILON >= self.time_step {
+            self.pending_time -= self.time_step;
+            self.step(self.time_step)?;
+        }
+        Ok(if self.complete {
+            TickOutcome::Finished
+        } else if self.is_stalled() {
+            TickOutcome::Stalled
+        } else {
+            TickOutcome::InProgress
+        })
+    }
+
+    /// Sets the length of the fixed step, in seconds, that the simulation is advanced by.
+    /// Shorter steps give smoother movement at a higher cost. Default is `1.0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `time_step` is not positive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut game = swarm_it::Swarm::<char>::new();
+    /// game.set_time_step(0.25);
+    /// approx::assert_abs_diff_eq!(game.get_time_step(), 0.25);
+    /// ```
+    pub fn set_time_step(&mut self, time_step: f64) {
+        assert!(time_step > 0.0, "Time step must be positive");
+        self.time_step = time_step;
+    }
+
+    /// Returns the length of the fixed step, in seconds
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let game = swarm_it::Swarm::<char>::new();
+    /// approx::assert_abs_diff_eq!(game.get_time_step(), 1.0);
+    /// ```
+    pub fn get_time_step(&self) -> f64 {
+        self.time_step
+    }
+
+    /// Returns the simulation time, in seconds, that has passed since the swarm was created
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut game = swarm_it::Swarm::<char>::new();
+    /// game.tick().unwrap();
+    /// game.tick_dt(0.5).unwrap();
+    /// approx::assert_abs_diff_eq!(game.get_time(), 1.0);
     /// ```
-    pub fn tick(&mut self) -> bool {
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    /// Sets how many seconds of simulated time without progress make the swarm stalled,
+    /// no matter how many steps they take (see `set_time_step()`).
+    /// Progress is made when the cargo is delivered to its target or dropped into the pit.
+    /// Any change of slots or carriers starts counting again. Default is `1000.0`.
+    ///
+    /// When the limit is reached the `Event::Stalled` is reported and `get_stall_report()` tells why.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `seconds` is not positive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut game = swarm_it::Swarm::<char>::new();
+    /// game.set_stall_limit_secs(500.0);
+    /// approx::assert_abs_diff_eq!(game.get_stall_limit_secs(), 500.0);
+    /// ```
+    pub fn set_stall_limit_secs(&mut self, seconds: f64) {
+        assert!(seconds > 0.0, "Stall limit must be positive");
+        self.stall.limit = seconds;
+    }
+
+    /// Returns the number of seconds without progress after which the swarm is considered stalled
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let game = swarm_it::Swarm::<char>::new();
+    /// approx::assert_abs_diff_eq!(game.get_stall_limit_secs(), 1000.0);
+    /// ```
+    pub fn get_stall_limit_secs(&self) -> f64 {
+        self.stall.limit
+    }
+
+    /// Returns the diagnostic if the swarm made no progress toward the target layout
+    /// for the number of seconds set with `set_stall_limit_secs()`, `None` otherwise
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_carrier(Carrier::new(0.0, 0.0));
+    /// game.tick().unwrap();
+    /// assert!(game.get_stall_report().is_none());
+    /// ```
+    pub fn get_stall_report(&self) -> Option<StallReport<T>> {
+        if self.stall.is_stalled() {
+            Some(StallReport::new(&self.stall, &self.count_cargo()))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the static obstacles. Carriers plan their paths around the blocked cells
+    /// and the distances between slots are measured along these paths.
+    /// Carriers that are already on their way plan the path again. Slot that carriers
+    /// can't get to is reported with `Event::Unreachable` and skipped until the obstacles are set again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// let mut obstacles = ObstacleGrid::new(0.0, 0.0, 10.0, 20, 20);
+    /// obstacles.block_rect(90.0, 0.0, 110.0, 150.0);
+    /// game.set_obstacles(obstacles);
+    /// assert!(game.get_obstacles().unwrap().is_blocked(100.0, 100.0));
+    /// ```
+    pub fn set_obstacles(&mut self, obstacles: ObstacleGrid) {
+        self.dispatcher.set_obstacles(Some(obstacles));
+        self.obstacles_changed();
+    }
+
+    /// Returns the static obstacles, if any
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let game = swarm_it::Swarm::<char>::new();
+    /// assert!(game.get_obstacles().is_none());
+    /// ```
+    pub fn get_obstacles(&self) -> Option<&ObstacleGrid> {
+        self.dispatcher.obstacles.as_ref()
+    }
+
+    /// Removes the static obstacles, carriers go straight to their targets again
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.set_obstacles(ObstacleGrid::new(0.0, 0.0, 10.0, 20, 20));
+    /// game.clear_obstacles();
+    /// assert!(game.get_obstacles().is_none());
+    /// ```
+    pub fn clear_obstacles(&mut self) {
+        self.dispatcher.set_obstacles(None);
+        self.obstacles_changed();
+    }
+
+    fn obstacles_changed(&mut self) {
+        self.stall.reset();
+        for (_, slot) in self.slots.iter_mut() {
+            slot.unreachable = false;
+        }
+        for (_, carrier) in self.carriers.iter_mut() {
+            carrier.path.clear();
+            carrier.path_target = None;
+        }
+    }
+
+    fn step(&mut self, dt: f64) -> Result<(), SwarmError> {
         self.tick_counter += 1;
-        let mut slots = &mut self.slots;
-        if self.first_tick {
-            self.dispatcher.precalc(&slots);
-            self.first_tick = false;
+        self.time += dt;
+        let first_event = self.events.len();
+        if self.slots_dirty {
+            self.dispatcher
+                .calculate_cargo_balance(&self.slots, &self.carriers);
+            self.slots_dirty = false;
             //_debug_dump_slot_distances(&slots, &self.dispatcher);
         }
-        self.dispatcher.conduct(&mut self.carriers, &mut slots);
-        self.carriers.iter_mut().for_each(|x| x.tick(slots));
-        self.job_finished()
+        let states = self.carrier_states();
+        self.dispatcher
+            .conduct(&mut self.carriers, &mut self.slots, &mut self.events)?;
+        if let Some(obstacles) = &self.dispatcher.obstacles {
+            for (carrier, slot) in plan_paths(obstacles, &mut self.carriers, &self.slots) {
+                self.dispatcher
+                    .slot_unreachable(slot, &mut self.carriers, &mut self.slots);
+                self.events.push(Event::Unreachable { carrier, slot });
+            }
+        }
+        self.record_state_changes(&states);
+        avoid_collisions(&mut self.carriers, dt);
+        for (index, carrier) in self.carriers.iter_mut() {
+            let from = carrier.state;
+            carrier.tick(index, &mut self.slots, &mut self.events, &mut self.rng, dt)?;
+            if carrier.state != from {
+                self.events.push(Event::StateChanged {
+                    carrier: index,
+                    from,
+                    to: carrier.state,
+                });
+            }
+        }
+        self.check_deadlines();
+        self.complete = self.is_layout_complete();
+        self.detect_stall(first_event, dt);
+        Ok(())
+    }
+
+    fn deadlines_changed(&mut self) {
+        self.deadline_check = Some(f64::NEG_INFINITY);
+    }
+
+    // Reports the slots that are not satisfied when their deadline passes
+    // and finds out when the next deadline is due
+    fn check_deadlines(&mut self) {
+        match self.deadline_check {
+            Some(time) if time <= self.time => (),
+            _ => return,
+        }
+        let mut next: Option<f64> = None;
+        for (index, slot) in self.slots.iter_mut() {
+            let deadline = match slot.get_deadline() {
+                Some(deadline) if !slot.deadline_checked => deadline,
+                _ => continue,
+            };
+            if deadline <= self.time {
+                slot.deadline_checked = true;
+                if !slot.is_satisfied() {
+                    self.events.push(Event::DeadlineMissed { slot: index });
+                }
+            } else {
+                next = Some(next.map_or(deadline, |next| next.min(deadline)));
+            }
+        }
+        self.deadline_check = next;
+    }
+
+    fn is_layout_complete(&self) -> bool {
+        self.slots
+            .values()
+            .all(|slot| slot.is_pit() || slot.is_spawner() || slot.is_satisfied())
+            && self
+                .carriers
+                .values()
+                .all(|carrier| carrier.payload.is_none() && carrier.hold.is_empty())
+    }
+
+    // Cargo in the slots and in the carriers against the cargo required by the target layout,
+    // in the order the cargo is first seen in the slots
+    fn count_cargo(&self) -> CargoBalance<T> {
+        let mut cargo_balance = CargoBalance::default();
+        let mut count = |cargo, delta| *cargo_balance.entry(cargo).or_insert(0) += delta;
+        for slot in self.slots.values() {
+            for payload in slot.get_contents() {
+                count(payload.cargo, 1);
+            }
+            for &cargo in slot.get_targets() {
+                count(cargo, -1);
+            }
+        }
+        for carrier in self.carriers.values() {
+            for payload in carrier.get_payloads() {
+                count(payload.cargo, 1);
+            }
+        }
+        // Removed at the end, so the balanced cargo doesn't change the order of the rest
+        cargo_balance.retain(|_, balance| *balance != 0);
+        cargo_balance
     }
 
-    /// Initiates some precalculation in order for the carriers
-    /// to be aware of modified slots. Call this function each time you
-    /// finished adding new slots or manually manipulating their data
-    /// through `get_slots_mut()`.
+    // Job is stalled if no cargo was delivered for too long or it can't be done at all
+    fn is_stalled(&self) -> bool {
+        !self.complete && (self.stall.is_stalled() || self.validate().is_err())
+    }
+
+    fn detect_stall(&mut self, first_event: usize, dt: f64) {
+        if self
+            .stall
+            .observe(&self.events[first_event..], self.complete, dt)
+        {
+            self.events.push(Event::Stalled {
+                seconds: self.stall.seconds(),
+            });
+        }
+    }
+
+    /// Makes the swarm count the cargo again from scratch before the next tick.
     ///
-    /// In the future I might eventually remove this obligation, but I need
-    /// to come up with some clever way of deciding whether a precalc is
-    /// needed or not. Invoking precalc after each single change is an overkill, since
-    /// one might want to add 100k slots and do the precalc only once at the end.
+    /// This is no longer required: slots added with `add_slot()`, changed with `update_slot()`
+    /// or accessed through `get_slots_mut()` are taken into account automatically.
+    /// Distances between slots are measured when needed.
     ///
     /// # Example
     ///
@@ -179,40 +689,95 @@ impl<T: PartialEq + Eq + Hash + Copy> Swarm<T> {
     /// let mut game = Swarm::<char>::new();
     /// game.slot_data_changed();
     /// ```
-    ///
-    /// # TODO
-    /// Different kind of that may change. Provide additional
-    /// parameter in order NOT to precalculate everything
-    /// 1. Slot payload => recalculate cargo balance
-    /// 2. Slots added/removed => recalculate slot distances
     pub fn slot_data_changed(&mut self) {
-        self.dispatcher.precalc(&self.slots);
+        self.slots_dirty = true;
+        self.stall.reset();
     }
 
-    fn add_object<U>(vec: &mut Vec<U>, obj: U) -> usize {
-        vec.push(obj);
-        vec.len() - 1
+    /// Returns the events that happened since the last call, oldest first.
+    /// Events are accumulated until drained, so call this regularly if
+    /// you're interested in them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_carrier(Carrier::new(0.0, 0.0));
+    /// game.add_slot(Slot::new(100.0, 100.0, Some(Payload::new('X')), None, SlotKind::CLASSIC));
+    /// game.add_slot(Slot::new(200.0, 100.0, None, Some(Payload::new('X')), SlotKind::CLASSIC));
+    /// while !game.tick().unwrap().is_finished() {
+    ///     for event in game.drain_events() {
+    ///         if let Event::PutDown { cargo, .. } = event {
+    ///             println!("Delivered {}", cargo);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, Event<T>> {
+        self.events.drain(..)
     }
 
-    fn all_carriers_idle(&self) -> bool {
-        !self.carriers.iter().any(|c| !c.state.is_idle())
+    fn carrier_states(&self) -> Vec<(CarrierId, State)> {
+        self.carriers
+            .iter()
+            .map(|(index, carrier)| (index, carrier.state))
+            .collect()
     }
 
-    fn job_finished(&mut self) -> bool {
-        if self.all_carriers_idle() {
-            self.idle_ticks += 1;
-            if self.idle_ticks == std::u8::MAX {
-                self.idle_ticks = 3;
-            }
-            if self.idle_ticks >= 2 {
-                return true;
+    fn record_state_changes(&mut self, states: &[(CarrierId, State)]) {
+        for &(index, from) in states {
+            if let Some(carrier) = self.carriers.get(index) {
+                if carrier.state != from {
+                    self.events.push(Event::StateChanged {
+                        carrier: index,
+                        from,
+                        to: carrier.state,
+                    });
+                }
             }
-        } else {
-            self.idle_ticks = 0;
         }
-        false
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn incremental_cargo_balance_matches_recount() {
+        let mut game = Swarm::with_seed(21);
+        let mut carrier = Carrier::new(0.0, 0.0);
+        carrier.set_capacity(2);
+        game.add_carrier(carrier);
+        game.add_carrier(Carrier::new(200.0, 0.0));
+
+        game.add_slot(Slot::new_bin(0.0, 100.0, 3, vec!['A', 'B', 'C'], vec!['B']));
+        game.add_slot(Slot::new(
+            100.0,
+            100.0,
+            Some(Payload::new('B')),
+            Some(Payload::new('A')),
+            SlotKind::CLASSIC,
+        ));
+        let late = game.add_slot(Slot::new(200.0, 100.0, None, None, SlotKind::CLASSIC));
+        game.add_slot(Slot::new(300.0, 100.0, None, None, SlotKind::PIT));
+        game.add_slot(Slot::new(300.0, 200.0, None, None, SlotKind::SPAWNER));
+
+        let mut recount = Dispatcher::new();
+        for tick in 0..5000 {
+            if tick == 50 {
+                game.update_slot(late, |slot| {
+                    slot.set_target_payload(Some(Payload::new('D')))
+                });
+            }
+            recount.calculate_cargo_balance(&game.slots, &game.carriers);
+            assert_eq!(game.dispatcher.cargo_balance, recount.cargo_balance);
+            if game.tick().unwrap().is_finished() {
+                assert!(game.slots.values().all(|slot| slot.is_satisfied()));
+                return;
+            }
+        }
+        panic!("Carriers did not finish the job");
+    }
+}
@@ -2,7 +2,7 @@
 #[macro_export]
 macro_rules! make_slot_pit {
     ($x: expr, $y: expr) => {
-        crate::Slot::new($x, $y, None, None, crate::SlotKind::PIT)
+        $crate::Slot::new($x, $y, None, None, $crate::SlotKind::PIT)
     };
 }
 
@@ -10,6 +10,6 @@ macro_rules! make_slot_pit {
 #[macro_export]
 macro_rules! make_slot_spawner {
     ($x: expr, $y: expr) => {
-        crate::Slot::new($x, $y, None, None, crate::SlotKind::SPAWNER)
+        $crate::Slot::new($x, $y, None, None, $crate::SlotKind::SPAWNER)
     };
 }
@@ -0,0 +1,517 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+
+use super::arena::*;
+use super::carrier::*;
+use super::position::*;
+use super::slot::*;
+use super::tools::*;
+
+const DIAGONAL_COST: f64 = std::f64::consts::SQRT_2;
+
+/// Occupancy grid that describes static obstacles (walls, shelving, etc.)
+/// Carriers plan their paths around the blocked cells and the distances between
+/// slots are measured along these paths.
+///
+/// Cells outside of the grid are considered free, but paths are only planned
+/// inside the grid, so make sure it covers all slots and carriers. Carriers are
+/// treated as points while planning, so block the cells generously
+/// if carriers have the radius set.
+///
+/// # Example
+///
+/// ```
+/// use swarm_it::*;
+/// let mut obstacles = ObstacleGrid::new(0.0, 0.0, 10.0, 50, 50);
+/// // Wall in the middle, with passage at the bottom
+/// obstacles.block_rect(240.0, 0.0, 260.0, 400.0);
+/// assert!(obstacles.is_blocked(250.0, 100.0));
+/// assert!(!obstacles.is_blocked(250.0, 450.0));
+///
+/// let mut game = Swarm::<char>::new();
+/// game.set_obstacles(obstacles);
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug)]
+pub struct ObstacleGrid {
+    origin: Position,
+    cell_size: f64,
+    columns: usize,
+    rows: usize,
+    blocked: Vec<bool>,
+}
+
+// Entry of the open set, ordered so the `BinaryHeap` pops the lowest cost first
+#[derive(PartialEq)]
+struct Node {
+    cost: f64,
+    cell: usize,
+}
+
+impl Eq for Node {}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ObstacleGrid {
+    /// Creates the grid with no obstacles. (`x`, `y`) is the corner of the first cell,
+    /// grid spans `columns` * `cell_size` to the right and `rows` * `cell_size` down.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let obstacles = swarm_it::ObstacleGrid::new(-100.0, -100.0, 20.0, 30, 30);
+    /// ```
+    pub fn new(x: f64, y: f64, cell_size: f64, columns: usize, rows: usize) -> ObstacleGrid {
+        ObstacleGrid {
+            origin: Position::new(x, y),
+            cell_size,
+            columns,
+            rows,
+            blocked: vec![false; columns * rows],
+        }
+    }
+
+    /// Marks the cell as blocked. Cells outside of the grid are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut obstacles = swarm_it::ObstacleGrid::new(0.0, 0.0, 10.0, 5, 5);
+    /// obstacles.block_cell(2, 3);
+    /// assert!(obstacles.is_blocked(25.0, 35.0));
+    /// ```
+    pub fn block_cell(&mut self, column: usize, row: usize) {
+        if column < self.columns && row < self.rows {
+            self.blocked[row * self.columns + column] = true;
+        }
+    }
+
+    /// Blocks all cells that overlap with the rectangle
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut obstacles = swarm_it::ObstacleGrid::new(0.0, 0.0, 10.0, 5, 5);
+    /// obstacles.block_rect(12.0, 12.0, 18.0, 28.0);
+    /// assert!(obstacles.is_blocked(15.0, 25.0));
+    /// assert!(!obstacles.is_blocked(25.0, 15.0));
+    /// ```
+    pub fn block_rect(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        let (first_column, first_row) = self.grid_coordinates(x1.min(x2), y1.min(y2));
+        let (last_column, last_row) = self.grid_coordinates(x1.max(x2), y1.max(y2));
+        for row in first_row.max(0)..=last_row.min(self.rows as i64 - 1) {
+            for column in first_column.max(0)..=last_column.min(self.columns as i64 - 1) {
+                self.block_cell(column as usize, row as usize);
+            }
+        }
+    }
+
+    /// Blocks all cells which center lies inside the polygon
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut obstacles = swarm_it::ObstacleGrid::new(0.0, 0.0, 10.0, 10, 10);
+    /// obstacles.block_polygon(&[(0.0, 0.0), (100.0, 0.0), (0.0, 100.0)]);
+    /// assert!(obstacles.is_blocked(15.0, 15.0));
+    /// assert!(!obstacles.is_blocked(85.0, 85.0));
+    /// ```
+    pub fn block_polygon(&mut self, points: &[(f64, f64)]) {
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let center = self.center_of(row * self.columns + column);
+                if is_inside_polygon(points, &center) {
+                    self.block_cell(column, row);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if the point lies in the blocked cell
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let obstacles = swarm_it::ObstacleGrid::new(0.0, 0.0, 10.0, 5, 5);
+    /// assert!(!obstacles.is_blocked(25.0, 25.0));
+    /// ```
+    pub fn is_blocked(&self, x: f64, y: f64) -> bool {
+        self.cell_of(&Position::new(x, y))
+            .is_some_and(|cell| self.blocked[cell])
+    }
+
+    fn grid_coordinates(&self, x: f64, y: f64) -> (i64, i64) {
+        (
+            ((x - self.origin.x) / self.cell_size).floor() as i64,
+            ((y - self.origin.y) / self.cell_size).floor() as i64,
+        )
+    }
+
+    fn cell_at(&self, column: i64, row: i64) -> Option<usize> {
+        if column < 0 || row < 0 || column >= self.columns as i64 || row >= self.rows as i64 {
+            None
+        } else {
+            Some(row as usize * self.columns + column as usize)
+        }
+    }
+
+    fn cell_of(&self, pos: &Position) -> Option<usize> {
+        let (column, row) = self.grid_coordinates(pos.x, pos.y);
+        self.cell_at(column, row)
+    }
+
+    fn center_of(&self, cell: usize) -> Position {
+        Position::new(
+            self.origin.x + ((cell % self.columns) as f64 + 0.5) * self.cell_size,
+            self.origin.y + ((cell / self.columns) as f64 + 0.5) * self.cell_size,
+        )
+    }
+
+    // Free neighbours of the cell together with the cost of moving there.
+    // Diagonal moves are not allowed to cut the corners of blocked cells.
+    fn neighbours(&self, cell: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let (column, row) = ((cell % self.columns) as i64, (cell / self.columns) as i64);
+        let is_free = move |dc: i64, dr: i64| {
+            self.cell_at(column + dc, row + dr)
+                .filter(|&cell| !self.blocked[cell])
+        };
+        [
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (1, -1),
+            (-1, 1),
+            (1, 1),
+        ]
+        .iter()
+        .filter_map(move |&(dc, dr)| {
+            let neighbour = is_free(dc, dr)?;
+            if dc != 0 && dr != 0 {
+                is_free(dc, 0)?;
+                is_free(0, dr)?;
+                Some((neighbour, DIAGONAL_COST * self.cell_size))
+            } else {
+                Some((neighbour, self.cell_size))
+            }
+        })
+    }
+
+    // Walks through all cells crossed by the segment (Amanatides-Woo traversal)
+    // and returns `false` if any of them is blocked
+    pub(crate) fn line_of_sight(&self, from: &Position, to: &Position) -> bool {
+        let (mut column, mut row) = self.grid_coordinates(from.x, from.y);
+        let (last_column, last_row) = self.grid_coordinates(to.x, to.y);
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let step_column = if dx > 0.0 { 1 } else { -1 };
+        let step_row = if dy > 0.0 { 1 } else { -1 };
+        let boundary = |start: f64, origin: f64, index: i64, step: i64| {
+            origin + (index + if step > 0 { 1 } else { 0 }) as f64 * self.cell_size - start
+        };
+        let mut t_max_x = if dx == 0.0 {
+            f64::INFINITY
+        } else {
+            boundary(from.x, self.origin.x, column, step_column) / dx
+        };
+        let mut t_max_y = if dy == 0.0 {
+            f64::INFINITY
+        } else {
+            boundary(from.y, self.origin.y, row, step_row) / dy
+        };
+        let t_delta_x = (self.cell_size / dx).abs();
+        let t_delta_y = (self.cell_size / dy).abs();
+
+        let steps = (last_column - column).abs() + (last_row - row).abs();
+        for _ in 0..=steps {
+            if let Some(cell) = self.cell_at(column, row) {
+                if self.blocked[cell] {
+                    return false;
+                }
+            }
+            if t_max_x < t_max_y {
+                column += step_column;
+                t_max_x += t_delta_x;
+            } else {
+                row += step_row;
+                t_max_y += t_delta_y;
+            }
+        }
+        true
+    }
+
+    /// Returns the waypoints that lead from `from` to `to` around the obstacles,
+    /// excluding both ends. Empty path means that the way is clear, which is also the case
+    /// if any of the ends lies outside of the grid, since paths are only planned inside.
+    /// Returns `None` if there is no way or any of the ends lies in the blocked cell.
+    pub(crate) fn find_path(&self, from: &Position, to: &Position) -> Option<Vec<Position>> {
+        let (start, goal) = match (self.cell_of(from), self.cell_of(to)) {
+            (Some(start), Some(goal)) => (start, goal),
+            _ => return Some(Vec::new()),
+        };
+        if self.blocked[start] || self.blocked[goal] {
+            return None;
+        }
+        if self.line_of_sight(from, to) {
+            return Some(Vec::new());
+        }
+
+        let goal_center = self.center_of(goal);
+        let heuristic = |cell: usize| {
+            let center = self.center_of(cell);
+            let (dx, dy) = (
+                (center.x - goal_center.x).abs(),
+                (center.y - goal_center.y).abs(),
+            );
+            dx.max(dy) + (DIAGONAL_COST - 1.0) * dx.min(dy)
+        };
+        let mut cost = vec![f64::INFINITY; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[start] = 0.0;
+        open.push(Node {
+            cost: heuristic(start),
+            cell: start,
+        });
+        while let Some(Node { cell, .. }) = open.pop() {
+            if cell == goal {
+                break;
+            }
+            for (neighbour, step) in self.neighbours(cell) {
+                let new_cost = cost[cell] + step;
+                if new_cost < cost[neighbour] {
+                    cost[neighbour] = new_cost;
+                    came_from[neighbour] = cell;
+                    open.push(Node {
+                        cost: new_cost + heuristic(neighbour),
+                        cell: neighbour,
+                    });
+                }
+            }
+        }
+        if !cost[goal].is_finite() {
+            return None;
+        }
+
+        let mut cells = Vec::new();
+        let mut cell = came_from[goal];
+        while cell != start && cell != usize::MAX {
+            cells.push(cell);
+            cell = came_from[cell];
+        }
+        let mut points = vec![*from];
+        points.extend(cells.iter().rev().map(|&cell| self.center_of(cell)));
+        points.push(*to);
+        Some(self.smooth(&points))
+    }
+
+    // Skips the points that can be reached directly, so carriers don't zig-zag from cell to cell
+    fn smooth(&self, points: &[Position]) -> Vec<Position> {
+        let last = points.len() - 1;
+        let mut waypoints = Vec::new();
+        let mut current = 0;
+        while current < last {
+            let next = (current + 1..=last)
+                .rev()
+                .find(|&next| self.line_of_sight(&points[current], &points[next]))
+                .unwrap_or(current + 1);
+            if next != last {
+                waypoints.push(points[next]);
+            }
+            current = next;
+        }
+        waypoints
+    }
+
+    // Length of the shortest path from the `from` position to each cell (Dijkstra)
+    fn distances_from(&self, from: &Position) -> Option<Vec<f64>> {
+        let start = self.cell_of(from)?;
+        let mut distances = vec![f64::INFINITY; self.blocked.len()];
+        if self.blocked[start] {
+            return Some(distances);
+        }
+        let mut open = BinaryHeap::new();
+        distances[start] = distance_between_positions(from, &self.center_of(start));
+        open.push(Node {
+            cost: distances[start],
+            cell: start,
+        });
+        while let Some(Node { cost, cell }) = open.pop() {
+            if cost > distances[cell] {
+                continue;
+            }
+            for (neighbour, step) in self.neighbours(cell) {
+                if cost + step < distances[neighbour] {
+                    distances[neighbour] = cost + step;
+                    open.push(Node {
+                        cost: cost + step,
+                        cell: neighbour,
+                    });
+                }
+            }
+        }
+        Some(distances)
+    }
+
+    // Returns the length of the way from `from` to each of the positions.
+    // Positions outside of the grid are measured in straight line,
+    // unreachable ones get `f64::INFINITY`.
+    pub(crate) fn path_lengths_from(&self, from: &Position, positions: &[Position]) -> Vec<f64> {
+        let distances = self.distances_from(from);
+        positions
+            .iter()
+            .map(|to| {
+                let straight = distance_between_positions(from, to);
+                match (&distances, self.cell_of(to)) {
+                    _ if self.line_of_sight(from, to) => straight,
+                    (Some(distances), Some(cell)) => {
+                        distances[cell] + distance_between_positions(&self.center_of(cell), to)
+                    }
+                    _ => straight,
+                }
+            })
+            .collect()
+    }
+}
+
+fn is_inside_polygon(points: &[(f64, f64)], pos: &Position) -> bool {
+    let mut inside = false;
+    let mut previous = match points.last() {
+        Some(&point) => point,
+        None => return false,
+    };
+    for &point in points {
+        if (point.1 > pos.y) != (previous.1 > pos.y)
+            && pos.x < (previous.0 - point.0) * (pos.y - point.1) / (previous.1 - point.1) + point.0
+        {
+            inside = !inside;
+        }
+        previous = point;
+    }
+    inside
+}
+
+// Plans the path for carriers that got a new target. Returns the carriers that can't get
+// to their targets, instead of letting them go straight through the obstacles.
+pub(crate) fn plan_paths<T: PartialEq + Eq + Hash + Copy>(
+    obstacles: &ObstacleGrid,
+    carriers: &mut Carriers<T>,
+    slots: &Slots<T>,
+) -> Vec<(CarrierId, SlotId)> {
+    let mut unreachable = Vec::new();
+    for (index, carrier) in carriers.iter_mut() {
+        let target = match carrier.state {
+            State::TARGETING(target) | State::MOVING(target) => target,
+            _ => continue,
+        };
+        if carrier.path_target == Some(target) {
+            continue;
+        }
+        if let Some(slot) = slots.get(target) {
+            match obstacles.find_path(carrier.get_position(), slot.get_position()) {
+                Some(path) => {
+                    carrier.path = path;
+                    carrier.path_target = Some(target);
+                }
+                None => unreachable.push((index, target)),
+            }
+        }
+    }
+    unreachable
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::navigation::*;
+
+    // 10x10 grid of 10 unit cells with the wall at column 5, open at the bottom row
+    fn grid_with_wall() -> ObstacleGrid {
+        let mut obstacles = ObstacleGrid::new(0.0, 0.0, 10.0, 10, 10);
+        obstacles.block_rect(50.0, 0.0, 59.0, 89.0);
+        obstacles
+    }
+
+    #[test]
+    fn line_of_sight() {
+        let obstacles = grid_with_wall();
+
+        assert!(obstacles.line_of_sight(&Position::new(5.0, 5.0), &Position::new(45.0, 85.0)));
+        assert!(obstacles.line_of_sight(&Position::new(5.0, 95.0), &Position::new(95.0, 95.0)));
+        assert!(!obstacles.line_of_sight(&Position::new(5.0, 5.0), &Position::new(95.0, 5.0)));
+        assert!(!obstacles.line_of_sight(&Position::new(95.0, 5.0), &Position::new(5.0, 50.0)));
+    }
+
+    #[test]
+    fn path_goes_around_the_wall() {
+        let obstacles = grid_with_wall();
+        let from = Position::new(5.0, 5.0);
+        let to = Position::new(95.0, 5.0);
+
+        let path = obstacles.find_path(&from, &to).unwrap();
+        assert!(!path.is_empty());
+        let mut previous = from;
+        for point in path.iter().chain(std::iter::once(&to)) {
+            assert!(obstacles.line_of_sight(&previous, point));
+            previous = *point;
+        }
+        assert!(path.iter().any(|point| point.y > 90.0));
+    }
+
+    #[test]
+    fn no_path_when_enclosed() {
+        let mut obstacles = ObstacleGrid::new(0.0, 0.0, 10.0, 10, 10);
+        obstacles.block_rect(50.0, 0.0, 59.0, 99.0);
+
+        assert_eq!(
+            obstacles
+                .find_path(&Position::new(5.0, 5.0), &Position::new(95.0, 5.0))
+                .map(|path| path.len()),
+            None
+        );
+    }
+
+    #[test]
+    fn ends_outside_of_the_grid_are_free() {
+        let obstacles = grid_with_wall();
+
+        assert_eq!(
+            obstacles
+                .find_path(&Position::new(5.0, 5.0), &Position::new(150.0, 5.0))
+                .map(|path| path.len()),
+            Some(0)
+        );
+        assert_eq!(
+            obstacles
+                .find_path(&Position::new(-50.0, 5.0), &Position::new(95.0, 5.0))
+                .map(|path| path.len()),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn path_lengths() {
+        let obstacles = grid_with_wall();
+        let positions = [Position::new(45.0, 5.0), Position::new(65.0, 5.0)];
+
+        let there = obstacles.path_lengths_from(&positions[0], &positions);
+        let back = obstacles.path_lengths_from(&positions[1], &positions);
+        assert_abs_diff_eq!(there[0], 0.0);
+        // Down to the bottom row, through the passage and up again
+        assert!(there[1] > 160.0);
+        assert_abs_diff_eq!(there[1], back[0], epsilon = 1e-9);
+    }
+}
@@ -1,10 +1,13 @@
+use super::arena::*;
+
 #[derive(Copy, Clone, Debug)]
 /// Represnets the `Payload` object. Payload is transferred around by Carriers.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Payload<T: PartialEq> {
     /// This is the actual payload
     pub cargo: T,
 
-    pub(crate) taken_from: Option<usize>,
+    pub(crate) taken_from: Option<SlotId>,
 }
 
 impl<T: PartialEq> Payload<T> {
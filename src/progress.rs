@@ -0,0 +1,112 @@
+use std::hash::Hash;
+
+use super::stall::*;
+use super::Swarm;
+
+/// Status of the job of transferring the layout to the target position
+///
+/// Variant   | Meaning
+/// ----------|--------
+/// Running   | Carriers are still working on the layout
+/// Completed | Every slot holds its target payloads and no carrier carries anything
+/// Stalled   | Target layout can't be reached, the `reason` tells what is short, what is in surplus and which carriers go in circles
+#[derive(Clone, PartialEq, Debug)]
+pub enum JobStatus<T> {
+    Running,
+    Completed,
+    Stalled { reason: StallReport<T> },
+}
+
+/// How far the swarm is from the target layout, see `Swarm::progress()`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Progress<T> {
+    matched_slots: usize,
+    mismatched_slots: usize,
+    cargo_balance: Vec<(T, i32)>,
+    status: JobStatus<T>,
+}
+
+impl<T> Progress<T> {
+    /// Returns the number of slots that hold their target payloads. Pits and spawners are not counted.
+    pub fn get_matched_slots(&self) -> usize {
+        self.matched_slots
+    }
+
+    /// Returns the number of slots that don't hold their target payloads yet
+    pub fn get_mismatched_slots(&self) -> usize {
+        self.mismatched_slots
+    }
+
+    /// Returns the cargo that is not balanced yet: positive count is the number of units
+    /// to be dropped into the pit, negative count is the number of units to be spawned.
+    /// Payloads held by the carriers are counted in. Cargo is listed in the order it first appears in the slots.
+    pub fn get_cargo_balance(&self) -> &[(T, i32)] {
+        &self.cargo_balance
+    }
+
+    /// Returns the status of the job
+    pub fn get_status(&self) -> &JobStatus<T> {
+        &self.status
+    }
+}
+
+impl<T: PartialEq + Eq + Hash + Copy> Swarm<T> {
+    /// Returns the progress toward the target layout as of the last tick.
+    /// Job is completed when every slot holds its target payloads and no carrier carries anything.
+    /// Swarm is stalled if the target layout fails the `validate()`,
+    /// or if no cargo was delivered for the number of seconds set with `set_stall_limit_secs()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_carrier(Carrier::new(0.0, 0.0));
+    /// game.add_slot(Slot::new(100.0, 0.0, Some(Payload::new('A')), None, SlotKind::CLASSIC));
+    /// game.add_slot(Slot::new(200.0, 0.0, None, Some(Payload::new('A')), SlotKind::CLASSIC));
+    /// game.add_slot(Slot::new(300.0, 0.0, None, Some(Payload::new('B')), SlotKind::CLASSIC));
+    ///
+    /// // There is no 'B' anywhere, so the job can't be completed
+    /// assert!(game.tick().unwrap().is_stalled());
+    /// for _ in 0..100 {
+    ///     game.tick().unwrap();
+    /// }
+    /// let progress = game.progress();
+    /// assert_eq!(progress.get_matched_slots(), 2);
+    /// assert_eq!(progress.get_mismatched_slots(), 1);
+    /// assert_eq!(progress.get_cargo_balance(), [('B', -1)]);
+    /// match progress.get_status() {
+    ///     JobStatus::Stalled { reason } => assert_eq!(reason.get_shortage(), [('B', 1)]),
+    ///     _ => panic!("there is no 'B' to put into the last slot"),
+    /// }
+    /// ```
+    pub fn progress(&self) -> Progress<T> {
+        let (matched_slots, mismatched_slots) = self
+            .slots
+            .values()
+            .filter(|slot| !slot.is_pit() && !slot.is_spawner())
+            .fold((0, 0), |(matched, mismatched), slot| {
+                if slot.is_satisfied() {
+                    (matched + 1, mismatched)
+                } else {
+                    (matched, mismatched + 1)
+                }
+            });
+        let cargo_balance = self.count_cargo();
+        let status = if self.complete {
+            JobStatus::Completed
+        } else if self.is_stalled() {
+            JobStatus::Stalled {
+                reason: StallReport::new(&self.stall, &cargo_balance),
+            }
+        } else {
+            JobStatus::Running
+        };
+        Progress {
+            matched_slots,
+            mismatched_slots,
+            cargo_balance: cargo_balance.into_iter().collect(),
+            status,
+        }
+    }
+}
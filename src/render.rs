@@ -0,0 +1,749 @@
+use std::fmt::{self, Display, Write as _};
+use std::hash::{Hash, Hasher};
+
+use super::position::*;
+use super::slot::*;
+use super::tools::*;
+use super::Swarm;
+
+const DEFAULT_SLOT_SIZE: f64 = 30.0;
+const BACKGROUND: Color = Color(255, 255, 255);
+const SLOT_OUTLINE: Color = Color(150, 150, 150);
+const PIT_FILL: Color = Color(50, 50, 50);
+const SPAWNER_FILL: Color = Color(170, 230, 170);
+const CARRIER_OUTLINE: Color = Color(0, 0, 0);
+const TARGET_LINE: Color = Color(60, 110, 220);
+const RESERVED_TARGET_LINE: Color = Color(240, 150, 30);
+const TEXT: Color = Color(0, 0, 0);
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Color(u8, u8, u8);
+
+impl Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+// Each kind of cargo gets its own color, the same in every run and every build
+fn cargo_color<T: Hash>(cargo: &T) -> Color {
+    let mut hasher = StableHasher::new();
+    cargo.hash(&mut hasher);
+    let hue = (hasher.finish() % 360) as f64 / 60.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    // Not too bright, so the labels are readable
+    let channel = |c: f64| (40.0 + c * 180.0) as u8;
+    Color(channel(r), channel(g), channel(b))
+}
+
+// Picture of the swarm in screen coordinates, drawn in order
+#[derive(Clone, Debug)]
+enum Shape {
+    Square {
+        center: Position,
+        size: f64,
+        fill: Option<Color>,
+        outline: Option<Color>,
+    },
+    Circle {
+        center: Position,
+        radius: f64,
+        fill: Option<Color>,
+        outline: Option<Color>,
+    },
+    Line {
+        from: Position,
+        to: Position,
+        color: Color,
+        dashed: bool,
+    },
+    Label {
+        pos: Position,
+        size: f64,
+        text: String,
+    },
+}
+
+/// Draws the current state of the swarm to SVG (`render` feature) and PNG or
+/// animated GIF (`raster` feature). Drawing doesn't require GPU or any system libraries.
+///
+/// Element        | Picture
+/// ---------------|--------
+/// Slot           | Gray square with the current cargo inside and the target cargo in the corner
+/// Pit            | Dark square
+/// Spawner        | Green square
+/// Carrier        | Circle with the line pointing where it is heading, carried cargo inside
+/// Target         | Blue line from the carrier to the slot it is driving to
+/// Reserved target| Dashed orange line from the carrier to the slot that waits for its payload
+///
+/// Each kind of cargo has its own color. SVG also shows the cargo as text,
+/// so the type of cargo must implement `Display`.
+///
+/// # Example
+///
+/// ```
+/// use swarm_it::*;
+/// let mut game = Swarm::<char>::new();
+/// game.add_slot(Slot::new(100.0, 100.0, Some(Payload::new('A')), Some(Payload::new('B')), SlotKind::CLASSIC));
+/// game.add_carrier(Carrier::new(0.0, 0.0));
+///
+/// let mut renderer = Renderer::new(320, 240);
+/// renderer.fit(&game);
+/// let svg = renderer.render_svg(&game);
+/// assert!(svg.starts_with("<svg"));
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Renderer {
+    width: u32,
+    height: u32,
+    origin: Position,
+    scale: f64,
+    slot_size: f64,
+}
+
+impl Renderer {
+    /// Creates the renderer that draws the pictures of given size in pixels.
+    /// By default the point (0, 0) of the swarm is in the top left corner
+    /// and one unit is one pixel.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let renderer = swarm_it::Renderer::new(640, 480);
+    /// ```
+    pub fn new(width: u32, height: u32) -> Renderer {
+        Renderer {
+            width,
+            height,
+            origin: Position::new(0.0, 0.0),
+            scale: 1.0,
+            slot_size: DEFAULT_SLOT_SIZE,
+        }
+    }
+
+    /// Sets the part of the swarm that is drawn: (`x`, `y`) goes to the top left corner
+    /// of the picture and one unit is `scale` pixels.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut renderer = swarm_it::Renderer::new(640, 480);
+    /// // Area from (-100, -100) to (540, 380), zoomed in twice
+    /// renderer.set_view(-100.0, -100.0, 2.0);
+    /// ```
+    pub fn set_view(&mut self, x: f64, y: f64, scale: f64) {
+        self.origin = Position::new(x, y);
+        self.scale = scale;
+    }
+
+    /// Sets the view, so all slots and carriers of the swarm fit into the picture
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_slot(Slot::new(1000.0, 1000.0, None, None, SlotKind::PIT));
+    /// let mut renderer = Renderer::new(640, 480);
+    /// renderer.fit(&game);
+    /// ```
+    pub fn fit<T: PartialEq + Eq + Hash + Copy>(&mut self, swarm: &Swarm<T>) {
+        let positions: Vec<&Position> = swarm
+            .get_slots()
+            .values()
+            .map(|slot| slot.get_position())
+            .chain(
+                swarm
+                    .get_carriers()
+                    .values()
+                    .map(|carrier| carrier.get_position()),
+            )
+            .collect();
+        let first = match positions.first() {
+            Some(first) => **first,
+            None => return,
+        };
+        let (min, max) = positions.iter().fold((first, first), |(min, max), p| {
+            (
+                Position::new(min.x.min(p.x), min.y.min(p.y)),
+                Position::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        });
+        let margin = self.slot_size;
+        let width = max.x - min.x + 2.0 * margin;
+        let height = max.y - min.y + 2.0 * margin;
+        let scale = (f64::from(self.width) / width).min(f64::from(self.height) / height);
+        // Center the swarm in the picture
+        let x = (min.x + max.x) / 2.0 - f64::from(self.width) / scale / 2.0;
+        let y = (min.y + max.y) / 2.0 - f64::from(self.height) / scale / 2.0;
+        self.set_view(x, y, scale);
+    }
+
+    /// Sets the size of the slot square, in units of the swarm. Carriers are drawn
+    /// in proportion to the slots, unless they have the radius set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut renderer = swarm_it::Renderer::new(640, 480);
+    /// renderer.set_slot_size(50.0);
+    /// ```
+    pub fn set_slot_size(&mut self, size: f64) {
+        self.slot_size = size;
+    }
+
+    fn screen_position(&self, pos: &Position) -> Position {
+        Position::new(
+            (pos.x - self.origin.x) * self.scale,
+            (pos.y - self.origin.y) * self.scale,
+        )
+    }
+
+    fn shapes<T: PartialEq + Eq + Hash + Copy + Display>(&self, swarm: &Swarm<T>) -> Vec<Shape> {
+        let mut shapes = Vec::new();
+        let size = self.slot_size * self.scale;
+        let slots = swarm.get_slots();
+
+        for slot in slots.values() {
+            let center = self.screen_position(slot.get_position());
+            let fill = match slot.get_kind() {
+                SlotKind::CLASSIC => None,
+                SlotKind::PIT => Some(PIT_FILL),
+                SlotKind::SPAWNER => Some(SPAWNER_FILL),
+            };
+            shapes.push(Shape::Square {
+                center,
+                size,
+                fill,
+                outline: Some(SLOT_OUTLINE),
+            });
+            if let Some(top) = slot.get_contents().last() {
+                shapes.push(Shape::Square {
+                    center,
+                    size: size * 0.6,
+                    fill: Some(cargo_color(&top.cargo)),
+                    outline: None,
+                });
+            }
+            if let Some(target) = slot.get_targets().first() {
+                shapes.push(Shape::Square {
+                    center: Position::new(center.x + size * 0.35, center.y + size * 0.35),
+                    size: size * 0.3,
+                    fill: Some(cargo_color(target)),
+                    outline: Some(SLOT_OUTLINE),
+                });
+            }
+            let contents: String = slot
+                .get_contents()
+                .iter()
+                .map(|p| p.cargo.to_string())
+                .collect();
+            if !contents.is_empty() {
+                shapes.push(Shape::Label {
+                    pos: center,
+                    size: size * 0.4,
+                    text: contents,
+                });
+            }
+            let targets: String = slot.get_targets().iter().map(|t| t.to_string()).collect();
+            if !targets.is_empty() {
+                shapes.push(Shape::Label {
+                    pos: Position::new(center.x, center.y + size * 0.8),
+                    size: size * 0.3,
+                    text: targets,
+                });
+            }
+        }
+
+        for carrier in swarm.get_carriers().values() {
+            let center = self.screen_position(carrier.get_position());
+            let lines = [
+                (carrier.get_target(), TARGET_LINE, false),
+                (carrier.get_reserved_target(), RESERVED_TARGET_LINE, true),
+            ];
+            for &(slot, color, dashed) in lines.iter() {
+                if let Some(slot) = slot.and_then(|slot| slots.get(slot)) {
+                    shapes.push(Shape::Line {
+                        from: center,
+                        to: self.screen_position(slot.get_position()),
+                        color,
+                        dashed,
+                    });
+                }
+            }
+        }
+
+        for carrier in swarm.get_carriers().values() {
+            let center = self.screen_position(carrier.get_position());
+            let radius = if carrier.get_radius() > 0.0 {
+                carrier.get_radius() * self.scale
+            } else {
+                size * 0.35
+            };
+            shapes.push(Shape::Circle {
+                center,
+                radius,
+                fill: Some(BACKGROUND),
+                outline: Some(CARRIER_OUTLINE),
+            });
+            let angle = carrier.get_angle();
+            shapes.push(Shape::Line {
+                from: center,
+                to: Position::new(
+                    center.x + angle.cos() * radius * 1.5,
+                    center.y + angle.sin() * radius * 1.5,
+                ),
+                color: CARRIER_OUTLINE,
+                dashed: false,
+            });
+            let payloads = carrier.get_payloads();
+            if let Some(payload) = payloads.first() {
+                shapes.push(Shape::Circle {
+                    center,
+                    radius: radius * 0.6,
+                    fill: Some(cargo_color(&payload.cargo)),
+                    outline: None,
+                });
+                shapes.push(Shape::Label {
+                    pos: center,
+                    size: radius,
+                    text: payloads.iter().map(|p| p.cargo.to_string()).collect(),
+                });
+            }
+        }
+        shapes
+    }
+
+    /// Returns the SVG picture of the current state of the swarm
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_slot(Slot::new(100.0, 100.0, Some(Payload::new('A')), None, SlotKind::CLASSIC));
+    /// let svg = Renderer::new(200, 200).render_svg(&game);
+    /// assert!(svg.contains(">A</text>"));
+    /// ```
+    pub fn render_svg<T: PartialEq + Eq + Hash + Copy + Display>(
+        &self,
+        swarm: &Swarm<T>,
+    ) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+             <rect width=\"{w}\" height=\"{h}\" fill=\"{}\"/>\n",
+            BACKGROUND,
+            w = self.width,
+            h = self.height
+        );
+        let paint = |fill: Option<Color>, outline: Option<Color>| {
+            format!(
+                "fill=\"{}\" stroke=\"{}\"",
+                fill.map_or("none".to_string(), |c| c.to_string()),
+                outline.map_or("none".to_string(), |c| c.to_string())
+            )
+        };
+        for shape in self.shapes(swarm) {
+            // Writing to the `String` never fails
+            let _ = match shape {
+                Shape::Square {
+                    center,
+                    size,
+                    fill,
+                    outline,
+                } => writeln!(
+                    svg,
+                    "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+                    center.x - size / 2.0,
+                    center.y - size / 2.0,
+                    size,
+                    size,
+                    paint(fill, outline)
+                ),
+                Shape::Circle {
+                    center,
+                    radius,
+                    fill,
+                    outline,
+                } => writeln!(
+                    svg,
+                    "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" {}/>",
+                    center.x,
+                    center.y,
+                    radius,
+                    paint(fill, outline)
+                ),
+                Shape::Line {
+                    from,
+                    to,
+                    color,
+                    dashed,
+                } => writeln!(
+                    svg,
+                    "<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"{}\"{}/>",
+                    from.x,
+                    from.y,
+                    to.x,
+                    to.y,
+                    color,
+                    if dashed {
+                        " stroke-dasharray=\"4 4\""
+                    } else {
+                        ""
+                    }
+                ),
+                Shape::Label { pos, size, text } => writeln!(
+                    svg,
+                    "<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\" font-family=\"monospace\" \
+                     text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+                    pos.x,
+                    pos.y,
+                    size,
+                    TEXT,
+                    escape(&text)
+                ),
+            };
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(feature = "raster")]
+mod raster {
+    use std::convert::TryFrom;
+    use std::error::Error;
+    use std::fmt::{self, Display};
+    use std::hash::Hash;
+    use std::io::Write;
+
+    use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Rect, Stroke, StrokeDash, Transform};
+
+    use super::*;
+
+    /// Errors that can be reported while rasterising the pictures
+    ///
+    /// Variant     | Meaning
+    /// ------------|--------
+    /// InvalidSize | Picture is empty or too large
+    /// Encoding    | PNG or GIF couldn't be encoded or written
+    #[derive(Debug)]
+    pub enum RenderError {
+        InvalidSize,
+        Encoding(Box<dyn Error + Send + Sync>),
+    }
+
+    impl Display for RenderError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                RenderError::InvalidSize => write!(f, "picture size is invalid"),
+                RenderError::Encoding(e) => write!(f, "picture couldn't be encoded: {}", e),
+            }
+        }
+    }
+
+    impl Error for RenderError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                RenderError::InvalidSize => None,
+                RenderError::Encoding(e) => Some(e.as_ref()),
+            }
+        }
+    }
+
+    fn paint(color: Color) -> Paint<'static> {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(color.0, color.1, color.2, 255);
+        paint.anti_alias = true;
+        paint
+    }
+
+    impl Renderer {
+        // Labels are not drawn, cargo is told apart by colors only
+        fn render_pixmap<T: PartialEq + Eq + Hash + Copy + Display>(
+            &self,
+            swarm: &Swarm<T>,
+        ) -> Result<Pixmap, RenderError> {
+            let mut pixmap =
+                Pixmap::new(self.width, self.height).ok_or(RenderError::InvalidSize)?;
+            pixmap.fill(tiny_skia::Color::from_rgba8(
+                BACKGROUND.0,
+                BACKGROUND.1,
+                BACKGROUND.2,
+                255,
+            ));
+            let solid = Stroke::default();
+            let draw = |pixmap: &mut Pixmap, path, fill: Option<Color>, outline: Option<Color>| {
+                if let Some(path) = path {
+                    if let Some(fill) = fill {
+                        pixmap.fill_path(
+                            &path,
+                            &paint(fill),
+                            FillRule::Winding,
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                    if let Some(outline) = outline {
+                        pixmap.stroke_path(
+                            &path,
+                            &paint(outline),
+                            &solid,
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                }
+            };
+            for shape in self.shapes(swarm) {
+                match shape {
+                    Shape::Square {
+                        center,
+                        size,
+                        fill,
+                        outline,
+                    } => {
+                        let rect = Rect::from_xywh(
+                            (center.x - size / 2.0) as f32,
+                            (center.y - size / 2.0) as f32,
+                            size as f32,
+                            size as f32,
+                        );
+                        draw(&mut pixmap, rect.map(PathBuilder::from_rect), fill, outline);
+                    }
+                    Shape::Circle {
+                        center,
+                        radius,
+                        fill,
+                        outline,
+                    } => {
+                        let circle = PathBuilder::from_circle(
+                            center.x as f32,
+                            center.y as f32,
+                            radius as f32,
+                        );
+                        draw(&mut pixmap, circle, fill, outline);
+                    }
+                    Shape::Line {
+                        from,
+                        to,
+                        color,
+                        dashed,
+                    } => {
+                        let mut builder = PathBuilder::new();
+                        builder.move_to(from.x as f32, from.y as f32);
+                        builder.line_to(to.x as f32, to.y as f32);
+                        if let Some(path) = builder.finish() {
+                            let mut stroke = Stroke::default();
+                            if dashed {
+                                stroke.dash = StrokeDash::new(vec![4.0, 4.0], 0.0);
+                            }
+                            pixmap.stroke_path(
+                                &path,
+                                &paint(color),
+                                &stroke,
+                                Transform::identity(),
+                                None,
+                            );
+                        }
+                    }
+                    Shape::Label { .. } => (),
+                }
+            }
+            Ok(pixmap)
+        }
+
+        /// Returns the PNG picture of the current state of the swarm. Unlike SVG,
+        /// it doesn't show the cargo as text, only as colors.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use swarm_it::*;
+        /// let mut game = Swarm::<char>::new();
+        /// game.add_slot(Slot::new(100.0, 100.0, Some(Payload::new('A')), None, SlotKind::CLASSIC));
+        /// let png = Renderer::new(200, 200).render_png(&game).unwrap();
+        /// assert_eq!(&png[1..4], b"PNG");
+        /// ```
+        pub fn render_png<T: PartialEq + Eq + Hash + Copy + Display>(
+            &self,
+            swarm: &Swarm<T>,
+        ) -> Result<Vec<u8>, RenderError> {
+            self.render_pixmap(swarm)?
+                .encode_png()
+                .map_err(|e| RenderError::Encoding(Box::new(e)))
+        }
+    }
+
+    /// Records the consecutive states of the swarm as frames of the animated GIF
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_slot(Slot::new(100.0, 100.0, Some(Payload::new('A')), None, SlotKind::CLASSIC));
+    /// game.add_slot(make_slot_pit!(300.0, 100.0));
+    /// game.add_carrier(Carrier::new(0.0, 0.0));
+    ///
+    /// let mut renderer = Renderer::new(160, 120);
+    /// renderer.fit(&game);
+    /// let mut gif = Vec::new();
+    /// let mut recorder = GifRecorder::new(&mut gif, renderer, 4).unwrap();
+    /// for _ in 0..10 {
+    ///     game.tick().unwrap();
+    ///     recorder.add_frame(&game).unwrap();
+    /// }
+    /// drop(recorder);
+    /// assert_eq!(&gif[0..3], b"GIF");
+    /// ```
+    pub struct GifRecorder<W: Write> {
+        encoder: gif::Encoder<W>,
+        renderer: Renderer,
+        delay: u16,
+    }
+
+    impl<W: Write> GifRecorder<W> {
+        /// Starts the endlessly repeated animation that is written to the `writer`.
+        /// Each frame is shown for `delay` hundredths of a second.
+        pub fn new(writer: W, renderer: Renderer, delay: u16) -> Result<Self, RenderError> {
+            let gif_size = |size: u32| u16::try_from(size).map_err(|_| RenderError::InvalidSize);
+            let mut encoder = gif::Encoder::new(
+                writer,
+                gif_size(renderer.width)?,
+                gif_size(renderer.height)?,
+                &[],
+            )
+            .map_err(|e| RenderError::Encoding(Box::new(e)))?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(|e| RenderError::Encoding(Box::new(e)))?;
+            Ok(GifRecorder {
+                encoder,
+                renderer,
+                delay,
+            })
+        }
+
+        /// Adds the picture of the current state of the swarm as the next frame
+        pub fn add_frame<T: PartialEq + Eq + Hash + Copy + Display>(
+            &mut self,
+            swarm: &Swarm<T>,
+        ) -> Result<(), RenderError> {
+            let mut pixmap = self.renderer.render_pixmap(swarm)?;
+            let mut frame = gif::Frame::from_rgba_speed(
+                pixmap.width() as u16,
+                pixmap.height() as u16,
+                pixmap.data_mut(),
+                10,
+            );
+            frame.delay = self.delay;
+            self.encoder
+                .write_frame(&frame)
+                .map_err(|e| RenderError::Encoding(Box::new(e)))
+        }
+    }
+}
+
+#[cfg(feature = "raster")]
+pub use raster::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::render::*;
+    use crate::*;
+
+    fn swarm() -> Swarm<char> {
+        let mut game = Swarm::with_seed(20);
+        game.add_slot(Slot::new(
+            0.0,
+            0.0,
+            Some(Payload::new('<')),
+            Some(Payload::new('B')),
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            200.0,
+            0.0,
+            Some(Payload::new('B')),
+            Some(Payload::new('<')),
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(make_slot_pit!(100.0, 200.0));
+        game.add_carrier(Carrier::new(100.0, 100.0));
+        game
+    }
+
+    #[test]
+    fn svg_shows_slots_carriers_and_targets() {
+        let mut game = swarm();
+        let mut renderer = Renderer::new(400, 300);
+        renderer.fit(&game);
+        let svg = renderer.render_svg(&game);
+        assert_eq!(svg.matches("<rect").count(), 1 + 3 + 2 + 2);
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.contains(">&lt;</text>"));
+        // Only the heading of the idle carrier
+        assert_eq!(svg.matches("<line").count(), 1);
+
+        let carrier = |game: &Swarm<char>| game.get_carriers().values().next().unwrap().clone();
+        while carrier(&game).get_payload().is_none() {
+            game.tick().unwrap();
+            let svg = renderer.render_svg(&game);
+            let carrier = carrier(&game);
+            let lines = 1
+                + carrier.get_target().map_or(0, |_| 1)
+                + carrier.get_reserved_target().map_or(0, |_| 1);
+            assert_eq!(svg.matches("<line").count(), lines);
+            assert_eq!(
+                svg.matches("stroke-dasharray").count(),
+                carrier.get_reserved_target().map_or(0, |_| 1)
+            );
+        }
+        let svg = renderer.render_svg(&game);
+        assert_eq!(svg.matches("<circle").count(), 2);
+    }
+
+    #[test]
+    fn fit_puts_everything_into_the_picture() {
+        let game = swarm();
+        let mut renderer = Renderer::new(400, 300);
+        renderer.fit(&game);
+        let positions = game
+            .get_slots()
+            .values()
+            .map(|slot| slot.get_position())
+            .chain(game.get_carriers().values().map(|c| c.get_position()));
+        for pos in positions {
+            let screen = renderer.screen_position(pos);
+            assert!(screen.x > 0.0 && screen.x < 400.0);
+            assert!(screen.y > 0.0 && screen.y < 300.0);
+        }
+    }
+
+    #[test]
+    fn cargo_colors_are_stable() {
+        assert_eq!(cargo_color(&'A').to_string(), "#28dc64");
+        assert_eq!(cargo_color(&'B').to_string(), "#2842dc");
+    }
+}
@@ -0,0 +1,428 @@
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::arena::*;
+use super::carrier::*;
+use super::error::*;
+use super::event::*;
+use super::navigation::*;
+use super::slot::*;
+use super::strategy::*;
+use super::tools::*;
+use super::Swarm;
+
+// Increased each time the format of the recording changes.
+// It is written in front of the recording, so it can be checked before the rest is decoded.
+const RECORDING_VERSION: u32 = 1;
+
+/// Change of the swarm made from the outside, between the ticks
+#[derive(Clone, Serialize, Deserialize)]
+enum Mutation<T: PartialEq + Eq + Hash + Copy> {
+    AddSlot(Slot<T>),
+    AddCarrier(Carrier<T>),
+    RemoveSlot(SlotId),
+    RemoveCarrier(CarrierId),
+    UpdateSlot { id: SlotId, slot: Slot<T> },
+    ReplaceSlots(Arena<SlotId, Slot<T>>),
+    ReplaceCarriers(Arena<CarrierId, Carrier<T>>),
+    SlotDataChanged,
+    SetObstacles(Option<ObstacleGrid>),
+    SetTimeStep(f64),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Entry<T: PartialEq + Eq + Hash + Copy> {
+    // `fingerprint` of the carrier positions after the tick
+    Tick {
+        dt: f64,
+        fingerprint: u64,
+    },
+    Mutation {
+        tick: u64,
+        mutation: Box<Mutation<T>>,
+    },
+}
+
+/// Run of the swarm captured by the `Recorder`: the initial state of the swarm,
+/// including the state of its random number generator, and everything
+/// that happened to it later. It is stored in the compact binary form.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Recording<T: PartialEq + Eq + Hash + Copy> {
+    initial: Vec<u8>,
+    entries: Vec<Entry<T>>,
+}
+
+/// Errors that can be reported while storing or replaying the recording
+///
+/// Variant  | Meaning
+/// ---------|--------
+/// Format   | Recording couldn't be written or read, or it was made by an incompatible version of the library
+/// Swarm    | Swarm reported the error while replaying
+/// Diverged | Carrier positions after the `tick` are different than when the run was recorded
+#[derive(Debug)]
+pub enum ReplayError {
+    Format(bincode::Error),
+    Swarm(SwarmError),
+    Diverged { tick: u64 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Format(e) => write!(f, "recording is malformed: {}", e),
+            ReplayError::Swarm(e) => write!(f, "swarm failed while replaying: {}", e),
+            ReplayError::Diverged { tick } => {
+                write!(f, "replay diverged from the recording at tick {}", tick)
+            }
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReplayError::Format(e) => Some(e),
+            ReplayError::Swarm(e) => Some(e),
+            ReplayError::Diverged { .. } => None,
+        }
+    }
+}
+
+impl From<bincode::Error> for ReplayError {
+    fn from(e: bincode::Error) -> Self {
+        ReplayError::Format(e)
+    }
+}
+
+impl From<SwarmError> for ReplayError {
+    fn from(e: SwarmError) -> Self {
+        ReplayError::Swarm(e)
+    }
+}
+
+// Positions are compared bit by bit, replay must be exact. Hash is the same in every build,
+// so the recording can be replayed by another build of the program.
+fn fingerprint<T: PartialEq + Eq + Hash + Copy>(swarm: &Swarm<T>) -> u64 {
+    let mut hasher = StableHasher::new();
+    for (id, carrier) in swarm.get_carriers().iter() {
+        id.hash(&mut hasher);
+        carrier.get_position().x.to_bits().hash(&mut hasher);
+        carrier.get_position().y.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+impl<T: PartialEq + Eq + Hash + Copy + Serialize + DeserializeOwned> Recording<T> {
+    /// Writes the recording
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut recorder = Recorder::new(Swarm::<char>::with_seed(1)).unwrap();
+    /// recorder.tick().unwrap();
+    ///
+    /// let mut file = Vec::new();
+    /// recorder.into_recording().write(&mut file).unwrap();
+    /// let recording = Recording::<char>::read(file.as_slice()).unwrap();
+    /// ```
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), ReplayError> {
+        bincode::serialize_into(&mut writer, &RECORDING_VERSION)?;
+        Ok(bincode::serialize_into(writer, self)?)
+    }
+
+    /// Reads the recording written with `write()`. Recording made by an incompatible
+    /// version of the library is rejected before the rest of it is read.
+    pub fn read<R: Read>(mut reader: R) -> Result<Recording<T>, ReplayError> {
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != RECORDING_VERSION {
+            return Err(ReplayError::Format(Box::new(bincode::ErrorKind::Custom(
+                format!("unsupported recording version {}", version),
+            ))));
+        }
+        Ok(bincode::deserialize_from(reader)?)
+    }
+
+    /// Returns the number of recorded ticks
+    pub fn ticks(&self) -> u64 {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry, Entry::Tick { .. }))
+            .count() as u64
+    }
+}
+
+/// Wraps the swarm and records everything that happens to it, so the run
+/// can be reproduced tick for tick with the `Replayer`.
+///
+/// The swarm must be changed through the recorder only. Changes made with `with_slots_mut()`
+/// and `with_carriers_mut()` are stored as the complete copy of all slots or carriers,
+/// so prefer `update_slot()` for the small changes.
+///
+/// The `DispatchStrategy` is not recorded, the replayer must use the same one.
+///
+/// # Example
+///
+/// ```
+/// use swarm_it::*;
+/// let mut recorder = Recorder::new(Swarm::<char>::with_seed(7)).unwrap();
+/// recorder.add_carrier(Carrier::new(0.0, 0.0));
+/// let slot = recorder.add_slot(Slot::new(100.0, 0.0, Some(Payload::new('A')), None, SlotKind::CLASSIC));
+/// recorder.add_slot(make_slot_pit!(200.0, 0.0));
+/// for _ in 0..100 {
+///     recorder.tick().unwrap();
+/// }
+/// recorder.update_slot(slot, |slot| slot.set_payloads(Some(Payload::new('B'))));
+/// for _ in 0..100 {
+///     recorder.tick().unwrap();
+/// }
+///
+/// let recording = recorder.into_recording();
+/// let mut replayer = Replayer::new(&recording).unwrap();
+/// replayer.run(true).unwrap();
+/// ```
+pub struct Recorder<T: PartialEq + Eq + Hash + Copy> {
+    swarm: Swarm<T>,
+    recording: Recording<T>,
+    tick: u64,
+}
+
+impl<T: PartialEq + Eq + Hash + Copy + Serialize + DeserializeOwned> Recorder<T> {
+    /// Starts recording the swarm in its current state
+    pub fn new(swarm: Swarm<T>) -> Result<Recorder<T>, ReplayError> {
+        let initial = bincode::serialize(&swarm)?;
+        Ok(Recorder {
+            swarm,
+            recording: Recording {
+                initial,
+                entries: Vec::new(),
+            },
+            tick: 0,
+        })
+    }
+
+    fn record(&mut self, mutation: Mutation<T>) {
+        self.recording.entries.push(Entry::Mutation {
+            tick: self.tick,
+            mutation: Box::new(mutation),
+        });
+    }
+
+    /// Returns the recorded swarm
+    pub fn get_swarm(&self) -> &Swarm<T> {
+        &self.swarm
+    }
+
+    /// Stops recording and returns the recording
+    pub fn into_recording(self) -> Recording<T> {
+        self.recording
+    }
+
+    /// Same as `Swarm::tick()`
+    pub fn tick(&mut self) -> Result<TickOutcome, SwarmError> {
+        self.tick_dt(1.0)
+    }
+
+    /// Same as `Swarm::tick_dt()`
+    pub fn tick_dt(&mut self, dt: f64) -> Result<TickOutcome, SwarmError> {
+        let outcome = self.swarm.tick_dt(dt);
+        self.tick += 1;
+        self.recording.entries.push(Entry::Tick {
+            dt,
+            fingerprint: fingerprint(&self.swarm),
+        });
+        outcome
+    }
+
+    /// Same as `Swarm::add_slot()`
+    pub fn add_slot(&mut self, slot: Slot<T>) -> SlotId {
+        self.record(Mutation::AddSlot(slot.clone()));
+        self.swarm.add_slot(slot)
+    }
+
+    /// Same as `Swarm::add_carrier()`
+    pub fn add_carrier(&mut self, carrier: Carrier<T>) -> CarrierId {
+        self.record(Mutation::AddCarrier(carrier.clone()));
+        self.swarm.add_carrier(carrier)
+    }
+
+    /// Same as `Swarm::remove_slot()`
+    pub fn remove_slot(&mut self, id: SlotId) -> Option<Slot<T>> {
+        self.record(Mutation::RemoveSlot(id));
+        self.swarm.remove_slot(id)
+    }
+
+    /// Same as `Swarm::remove_carrier()`
+    pub fn remove_carrier(&mut self, id: CarrierId) -> Option<Carrier<T>> {
+        self.record(Mutation::RemoveCarrier(id));
+        self.swarm.remove_carrier(id)
+    }
+
+    /// Same as `Swarm::update_slot()`
+    pub fn update_slot<R, F: FnOnce(&mut Slot<T>) -> R>(&mut self, id: SlotId, f: F) -> Option<R> {
+        let result = self.swarm.update_slot(id, f)?;
+        let slot = self.swarm.get_slots()[id].clone();
+        self.record(Mutation::UpdateSlot { id, slot });
+        Some(result)
+    }
+
+    /// Changes the slots with the function `f`, as if they were accessed
+    /// through `Swarm::get_slots_mut()`, and returns its result
+    pub fn with_slots_mut<R, F: FnOnce(&mut Arena<SlotId, Slot<T>>) -> R>(&mut self, f: F) -> R {
+        let result = f(self.swarm.get_slots_mut());
+        self.record(Mutation::ReplaceSlots(self.swarm.get_slots().clone()));
+        result
+    }
+
+    /// Changes the carriers with the function `f`, as if they were accessed
+    /// through `Swarm::get_carriers_mut()`, and returns its result
+    pub fn with_carriers_mut<R, F: FnOnce(&mut Arena<CarrierId, Carrier<T>>) -> R>(
+        &mut self,
+        f: F,
+    ) -> R {
+        let result = f(self.swarm.get_carriers_mut());
+        self.record(Mutation::ReplaceCarriers(self.swarm.get_carriers().clone()));
+        result
+    }
+
+    /// Same as `Swarm::slot_data_changed()`
+    pub fn slot_data_changed(&mut self) {
+        self.record(Mutation::SlotDataChanged);
+        self.swarm.slot_data_changed();
+    }
+
+    /// Same as `Swarm::set_obstacles()`
+    pub fn set_obstacles(&mut self, obstacles: ObstacleGrid) {
+        self.record(Mutation::SetObstacles(Some(obstacles.clone())));
+        self.swarm.set_obstacles(obstacles);
+    }
+
+    /// Same as `Swarm::clear_obstacles()`
+    pub fn clear_obstacles(&mut self) {
+        self.record(Mutation::SetObstacles(None));
+        self.swarm.clear_obstacles();
+    }
+
+    /// Same as `Swarm::set_time_step()`
+    pub fn set_time_step(&mut self, time_step: f64) {
+        self.record(Mutation::SetTimeStep(time_step));
+        self.swarm.set_time_step(time_step);
+    }
+
+    /// Same as `Swarm::set_strategy()`. Strategy is not recorded.
+    pub fn set_strategy<S: DispatchStrategy<T> + 'static>(&mut self, strategy: S) {
+        self.swarm.set_strategy(strategy);
+    }
+
+    /// Same as `Swarm::drain_events()`
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, Event<T>> {
+        self.swarm.drain_events()
+    }
+}
+
+/// Reproduces the run captured by the `Recorder`
+pub struct Replayer<T: PartialEq + Eq + Hash + Copy> {
+    swarm: Swarm<T>,
+    entries: Vec<Entry<T>>,
+    next: usize,
+    tick: u64,
+}
+
+impl<T: PartialEq + Eq + Hash + Copy + Serialize + DeserializeOwned> Replayer<T> {
+    /// Restores the initial state of the recorded swarm. It uses the `GreedyStrategy`,
+    /// set another one with `set_strategy()` if the run was recorded with it.
+    pub fn new(recording: &Recording<T>) -> Result<Replayer<T>, ReplayError> {
+        Ok(Replayer {
+            swarm: bincode::deserialize(&recording.initial)?,
+            entries: recording.entries.clone(),
+            next: 0,
+            tick: 0,
+        })
+    }
+
+    /// Sets the strategy, it must be the same as the one used while recording
+    pub fn set_strategy<S: DispatchStrategy<T> + 'static>(&mut self, strategy: S) {
+        self.swarm.set_strategy(strategy);
+    }
+
+    /// Returns the replayed swarm
+    pub fn get_swarm(&self) -> &Swarm<T> {
+        &self.swarm
+    }
+
+    /// Returns the replayed swarm. Events can be drained from it while replaying.
+    pub fn get_swarm_mut(&mut self) -> &mut Swarm<T> {
+        &mut self.swarm
+    }
+
+    /// Returns the number of ticks replayed so far
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Applies the changes recorded before the next tick and makes the tick.
+    /// Returns `None` when the whole recording is replayed.
+    ///
+    /// With `verify` set, positions of the carriers after the tick are compared
+    /// to the recorded ones and `ReplayError::Diverged` is reported if they differ.
+    pub fn step(&mut self, verify: bool) -> Option<Result<TickOutcome, ReplayError>> {
+        while let Some(entry) = self.entries.get(self.next) {
+            self.next += 1;
+            match entry {
+                Entry::Mutation { mutation, .. } => self.apply(*mutation.clone()),
+                &Entry::Tick {
+                    dt,
+                    fingerprint: recorded,
+                } => {
+                    self.tick += 1;
+                    // Errors are recorded as well, so they must happen again
+                    let outcome = self.swarm.tick_dt(dt);
+                    if verify && fingerprint(&self.swarm) != recorded {
+                        return Some(Err(ReplayError::Diverged { tick: self.tick }));
+                    }
+                    return Some(outcome.map_err(ReplayError::from));
+                }
+            }
+        }
+        None
+    }
+
+    /// Replays the rest of the recording, see `step()`
+    pub fn run(&mut self, verify: bool) -> Result<(), ReplayError> {
+        while let Some(outcome) = self.step(verify) {
+            outcome?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, mutation: Mutation<T>) {
+        match mutation {
+            Mutation::AddSlot(slot) => {
+                self.swarm.add_slot(slot);
+            }
+            Mutation::AddCarrier(carrier) => {
+                self.swarm.add_carrier(carrier);
+            }
+            Mutation::RemoveSlot(id) => {
+                self.swarm.remove_slot(id);
+            }
+            Mutation::RemoveCarrier(id) => {
+                self.swarm.remove_carrier(id);
+            }
+            Mutation::UpdateSlot { id, slot } => {
+                self.swarm.update_slot(id, |s| *s = slot);
+            }
+            Mutation::ReplaceSlots(slots) => *self.swarm.get_slots_mut() = slots,
+            Mutation::ReplaceCarriers(carriers) => *self.swarm.get_carriers_mut() = carriers,
+            Mutation::SlotDataChanged => self.swarm.slot_data_changed(),
+            Mutation::SetObstacles(Some(obstacles)) => self.swarm.set_obstacles(obstacles),
+            Mutation::SetObstacles(None) => self.swarm.clear_obstacles(),
+            Mutation::SetTimeStep(time_step) => self.swarm.set_time_step(time_step),
+        }
+    }
+}
@@ -1,33 +1,56 @@
+use super::arena::*;
 use super::payload::*;
 use super::position::*;
 
+use std::cmp::Ordering;
 use std::hash::Hash;
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 /// Library supports different kind of slots
 ///
 /// Type    | Meaning
 /// --------|--------
-/// CLASSIC | Slot with the ability to store single payload
+/// CLASSIC | Slot with the ability to store single payload, or several of them if it is a bin (see `Slot::new_bin()`)
 /// PIT     | Slot which is always empty and carriers can drop anything into it (i.e. payload that is of no use and must be removed)
 /// SPAWNER | Slot which produces any payload that might be needed by carriers to fill the payload shortage
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SlotKind {
     CLASSIC,
     PIT,
     SPAWNER,
 }
 
+pub(crate) type Slots<T> = Arena<SlotId, Slot<T>>;
+
 /// Represnets the `Slot` object. Slots have their target payload specified
 /// and carriers will do their best to find appropriate payload and
 /// bring it to the slot.
-#[derive(Copy, Clone, Debug)]
+///
+/// Bin is a slot that holds up to `capacity` payloads, like a shelf or a box. Its target is
+/// a multiset of cargo, the order of payloads doesn't matter. Carriers pick up
+/// and put down one payload at a time and any payload can be taken out of the bin,
+/// no matter which ones were put in after it.
+///
+/// Since the bin owns its payloads, `Slot` is `Clone`, but no longer `Copy`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Slot<T: PartialEq + Eq + Hash + Copy> {
     // TODO: Do not require Copy - get_payloads() should return reference
     pos: Position,
-    pub(crate) current_payload: Option<Payload<T>>,
-    target_payload: Option<Payload<T>>,
+    pub(crate) contents: Vec<Payload<T>>,
+    targets: Vec<T>,
+    capacity: usize,
     pub(crate) taken_care_of: bool,
+    // Carrier couldn't find the way to the slot around the obstacles
+    pub(crate) unreachable: bool,
     kind: SlotKind,
+    #[cfg_attr(feature = "serde", serde(default))]
+    priority: i32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    deadline: Option<f64>,
+    // Deadline has passed and the slot was checked, so it is not reported again
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) deadline_checked: bool,
 }
 
 impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
@@ -52,10 +75,48 @@ impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
     ) -> Slot<T> {
         Slot {
             pos: Position::new(x, y),
-            current_payload,
-            target_payload,
+            contents: current_payload.into_iter().collect(),
+            targets: target_payload.map(|p| p.cargo).into_iter().collect(),
+            capacity: 1,
             taken_care_of: false,
+            unreachable: false,
             kind,
+            priority: 0,
+            deadline: None,
+            deadline_checked: false,
+        }
+    }
+
+    /// Creates new bin at the position specified. Bin holds up to `capacity` payloads,
+    /// `current` is its content and `target` is the cargo it should hold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `current` or `target` doesn't fit into the bin.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let bin = swarm_it::Slot::new_bin(100.0, 100.0, 4, vec!['X', 'X', 'Y'], vec!['X', 'Z']);
+    /// assert_eq!(bin.get_contents().len(), 3);
+    /// assert_eq!(bin.get_targets(), &['X', 'Z']);
+    /// ```
+    pub fn new_bin(x: f64, y: f64, capacity: usize, current: Vec<T>, target: Vec<T>) -> Slot<T> {
+        assert!(
+            current.len() <= capacity && target.len() <= capacity,
+            "Payloads do not fit into the bin"
+        );
+        Slot {
+            pos: Position::new(x, y),
+            contents: current.into_iter().map(Payload::new).collect(),
+            targets: target,
+            capacity,
+            taken_care_of: false,
+            unreachable: false,
+            kind: SlotKind::CLASSIC,
+            priority: 0,
+            deadline: None,
+            deadline_checked: false,
         }
     }
 
@@ -75,7 +136,8 @@ impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
         &self.pos
     }
 
-    /// Returns current carrier payloads.
+    /// Returns current slot payloads. For bins these are the payload
+    /// put in first and the first target, see `get_contents()` and `get_targets()`.
     ///
     /// Index | Content
     /// ------|---------------
@@ -95,7 +157,85 @@ impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
     /// assert_eq!(payloads[1], None);
     /// ```
     pub fn get_payloads(&self) -> [Option<Payload<T>>; 2] {
-        [self.current_payload, self.target_payload]
+        [
+            self.contents.first().copied(),
+            self.targets.first().map(|&cargo| Payload::new(cargo)),
+        ]
+    }
+
+    /// Returns all payloads held by the slot, in the order they were put in
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let bin = swarm_it::Slot::new_bin(100.0, 100.0, 4, vec!['X', 'Y'], vec![]);
+    /// assert_eq!(bin.get_contents(), &[swarm_it::Payload::new('X'), swarm_it::Payload::new('Y')]);
+    /// ```
+    pub fn get_contents(&self) -> &[Payload<T>] {
+        &self.contents
+    }
+
+    /// Returns the cargo that the slot should hold
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let bin = swarm_it::Slot::new_bin(100.0, 100.0, 4, vec![], vec!['X', 'X']);
+    /// assert_eq!(bin.get_targets(), &['X', 'X']);
+    /// ```
+    pub fn get_targets(&self) -> &[T] {
+        &self.targets
+    }
+
+    /// Sets the cargo that the slot should hold
+    ///
+    /// # Panics
+    ///
+    /// Panics if `targets` doesn't fit into the slot.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut bin = swarm_it::Slot::new_bin(100.0, 100.0, 4, vec![], vec![]);
+    /// bin.set_targets(vec!['X', 'Y', 'X']);
+    /// assert_eq!(bin.get_targets(), &['X', 'Y', 'X']);
+    /// ```
+    pub fn set_targets(&mut self, targets: Vec<T>) {
+        assert!(
+            targets.len() <= self.capacity,
+            "Payloads do not fit into the slot"
+        );
+        self.targets = targets;
+    }
+
+    /// Returns how many payloads the slot can hold
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let slot = swarm_it::Slot::<char>::new(100.0, 100.0, None, None, swarm_it::SlotKind::CLASSIC);
+    /// let bin = swarm_it::Slot::<char>::new_bin(100.0, 100.0, 4, vec![], vec![]);
+    /// assert_eq!(slot.get_capacity(), 1);
+    /// assert_eq!(bin.get_capacity(), 4);
+    /// ```
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns `true` if the slot holds exactly the cargo it should, regardless of the order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let bin = swarm_it::Slot::new_bin(100.0, 100.0, 4, vec!['X', 'Y'], vec!['Y', 'X']);
+    /// assert!(bin.is_satisfied());
+    /// ```
+    pub fn is_satisfied(&self) -> bool {
+        self.contents.len() == self.targets.len()
+            && self
+                .contents
+                .iter()
+                .all(|p| self.count_current(p.cargo) == self.count_target(p.cargo))
     }
 
     /// Sets target payload
@@ -110,7 +250,7 @@ impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
     /// assert_eq!(payloads[1], Some(swarm_it::Payload::new('X')));
     /// ```
     pub fn set_target_payload(&mut self, p: Option<Payload<T>>) {
-        self.target_payload = p;
+        self.targets = p.map(|p| p.cargo).into_iter().collect();
     }
 
     /// Sets both current and target payloads
@@ -125,8 +265,8 @@ impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
     /// assert_eq!(payloads[1], Some(swarm_it::Payload::new('X')));
     /// ```
     pub fn set_payloads(&mut self, p: Option<Payload<T>>) {
-        self.current_payload = p;
-        self.target_payload = p;
+        self.contents = p.into_iter().collect();
+        self.set_target_payload(p);
     }
 
     /// Returns `true` if this slot is already addressed by any of the carriers.
@@ -144,6 +284,19 @@ impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
         self.taken_care_of
     }
 
+    /// Returns `true` if carriers couldn't find the way to the slot around the obstacles.
+    /// Such slot is skipped until the obstacles are set again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let slot = swarm_it::Slot::<char>::new(100.0, 100.0, None, None, swarm_it::SlotKind::CLASSIC);
+    /// assert_eq!(slot.is_unreachable(), false);
+    /// ```
+    pub fn is_unreachable(&self) -> bool {
+        self.unreachable
+    }
+
     /// Returns `true` is slot is a pit
     ///
     /// # Example
@@ -160,6 +313,18 @@ impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
         self.kind == SlotKind::PIT
     }
 
+    /// Returns the kind of the slot
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let slot = swarm_it::Slot::<char>::new(100.0, 100.0, None, None, swarm_it::SlotKind::PIT);
+    /// assert_eq!(slot.get_kind(), swarm_it::SlotKind::PIT);
+    /// ```
+    pub fn get_kind(&self) -> SlotKind {
+        self.kind
+    }
+
     /// Returns `true` is slot is a spawner
     ///
     /// # Example
@@ -176,7 +341,169 @@ impl<T: PartialEq + Eq + Hash + Copy> Slot<T> {
         self.kind == SlotKind::SPAWNER
     }
 
-    pub(crate) fn accepts(&self, p: Option<Payload<T>>) -> bool {
-        self.target_payload == p
+    /// Sets the priority of the slot. Slots with higher priority get their payloads first.
+    /// Default priority is `0`, negative priority makes the slot less important than others.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut slot = swarm_it::Slot::<char>::new(100.0, 100.0, None, None, swarm_it::SlotKind::CLASSIC);
+    /// slot.set_priority(10);
+    /// assert_eq!(slot.get_priority(), 10);
+    /// ```
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+
+    /// Returns the priority of the slot
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let slot = swarm_it::Slot::<char>::new(100.0, 100.0, None, None, swarm_it::SlotKind::CLASSIC);
+    /// assert_eq!(slot.get_priority(), 0);
+    /// ```
+    pub fn get_priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Sets the simulation time, in seconds (see `Swarm::get_time()`), by which the slot
+    /// should hold its target payloads. Among the slots of the same priority, the one
+    /// with the earliest deadline is served first. Slot that is not satisfied in time
+    /// is reported with `Event::DeadlineMissed`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut slot = swarm_it::Slot::<char>::new(100.0, 100.0, None, None, swarm_it::SlotKind::CLASSIC);
+    /// slot.set_deadline(Some(120.0));
+    /// assert_eq!(slot.get_deadline(), Some(120.0));
+    /// ```
+    pub fn set_deadline(&mut self, deadline: Option<f64>) {
+        self.deadline = deadline;
+        self.deadline_checked = false;
+    }
+
+    /// Returns the deadline of the slot, if any
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let slot = swarm_it::Slot::<char>::new(100.0, 100.0, None, None, swarm_it::SlotKind::CLASSIC);
+    /// assert_eq!(slot.get_deadline(), None);
+    /// ```
+    pub fn get_deadline(&self) -> Option<f64> {
+        self.deadline
+    }
+
+    // Ordering of the slots from the most urgent one: higher priority first,
+    // then the earlier deadline, slots without deadline last
+    pub(crate) fn cmp_urgency(&self, other: &Slot<T>) -> Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| match (self.deadline, other.deadline) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
+    }
+
+    fn count_current(&self, cargo: T) -> usize {
+        self.contents.iter().filter(|p| p.cargo == cargo).count()
+    }
+
+    fn count_target(&self, cargo: T) -> usize {
+        self.targets.iter().filter(|&&c| c == cargo).count()
+    }
+
+    pub(crate) fn has_room(&self) -> bool {
+        self.contents.len() < self.capacity
+    }
+
+    // Slot still misses some of this cargo and there is room to put it down
+    pub(crate) fn accepts(&self, p: &Payload<T>) -> bool {
+        self.has_room() && self.count_target(p.cargo) > self.count_current(p.cargo)
+    }
+
+    // Slot holds more of this cargo than it should
+    pub(crate) fn has_surplus(&self, cargo: T) -> bool {
+        self.count_current(cargo) > self.count_target(cargo)
+    }
+
+    pub(crate) fn has_any_surplus(&self) -> bool {
+        self.contents.iter().any(|p| self.has_surplus(p.cargo))
+    }
+
+    // Cargo that is held in excess, each kind once, in the order it was put in
+    pub(crate) fn surplus(&self) -> Vec<T> {
+        let mut surplus: Vec<T> = Vec::new();
+        for p in &self.contents {
+            if !surplus.contains(&p.cargo) && self.has_surplus(p.cargo) {
+                surplus.push(p.cargo);
+            }
+        }
+        surplus
+    }
+
+    // Removes the latest payload of the cargo given, or the latest
+    // surplus payload if no cargo is specified
+    pub(crate) fn take(&mut self, cargo: Option<T>) -> Option<Payload<T>> {
+        let index = match cargo {
+            Some(cargo) => self.contents.iter().rposition(|p| p.cargo == cargo),
+            None => self
+                .contents
+                .iter()
+                .rposition(|p| self.has_surplus(p.cargo))
+                .or_else(|| self.contents.len().checked_sub(1)),
+        }?;
+        Some(self.contents.remove(index))
+    }
+
+    pub(crate) fn put(&mut self, payload: Payload<T>) {
+        self.contents.push(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slot::*;
+
+    #[test]
+    fn bin_accepts_missing_cargo_only() {
+        let mut bin = Slot::new_bin(0.0, 0.0, 3, vec!['A', 'B'], vec!['A', 'A', 'C']);
+
+        assert!(bin.accepts(&Payload::new('A')));
+        assert!(bin.accepts(&Payload::new('C')));
+        assert!(!bin.accepts(&Payload::new('B')));
+        assert_eq!(bin.surplus(), vec!['B']);
+
+        // Full bin accepts nothing, until the surplus is taken away
+        bin.put(Payload::new('A'));
+        assert!(!bin.accepts(&Payload::new('C')));
+        assert_eq!(bin.take(None), Some(Payload::new('B')));
+        assert!(bin.accepts(&Payload::new('C')));
+        bin.put(Payload::new('C'));
+        assert!(bin.is_satisfied());
+    }
+
+    #[test]
+    fn bin_gives_any_payload_of_cargo() {
+        let mut bin = Slot::new_bin(0.0, 0.0, 4, vec!['A', 'B', 'A', 'C'], vec!['A', 'C']);
+        let cargo = |bin: &Slot<char>| -> Vec<char> {
+            bin.get_contents().iter().map(|p| p.cargo).collect()
+        };
+
+        // Payloads put in after the requested one stay in place
+        assert_eq!(bin.take(Some('A')), Some(Payload::new('A')));
+        assert_eq!(cargo(&bin), vec!['A', 'B', 'C']);
+        // Without the cargo given, the latest surplus payload is taken
+        assert_eq!(bin.take(None), Some(Payload::new('B')));
+        assert_eq!(cargo(&bin), vec!['A', 'C']);
+        assert_eq!(bin.take(Some('B')), None);
+        // New payloads go at the end
+        bin.put(Payload::new('D'));
+        assert_eq!(cargo(&bin), vec!['A', 'C', 'D']);
     }
 }
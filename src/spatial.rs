@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use super::arena::*;
+use super::position::*;
+use super::tools::*;
+
+// Index is built again when it holds this many times more slots than when it was built,
+// otherwise the cells would get crowded
+const REBUILD_GROWTH: usize = 4;
+
+type Cell = (i64, i64);
+
+// Uniform grid over the slot positions. Cell size is chosen when the index is built,
+// so that there is about one slot per cell.
+#[derive(Clone, Debug)]
+pub(crate) struct SpatialIndex {
+    cell_size: f64,
+    cells: HashMap<Cell, Vec<(SlotId, Position)>>,
+    len: usize,
+    built_len: usize,
+    // Range of cells that have ever been occupied, the search never goes beyond it
+    bounds: Option<(Cell, Cell)>,
+}
+
+impl SpatialIndex {
+    pub(crate) fn build(points: Vec<(SlotId, Position)>) -> SpatialIndex {
+        let mut index = SpatialIndex {
+            cell_size: SpatialIndex::cell_size_for(&points),
+            cells: HashMap::new(),
+            len: 0,
+            built_len: points.len(),
+            bounds: None,
+        };
+        points
+            .into_iter()
+            .for_each(|(id, pos)| index.insert(id, pos));
+        index
+    }
+
+    fn cell_size_for(points: &[(SlotId, Position)]) -> f64 {
+        let (min, max) = match points.first() {
+            Some((_, first)) => points.iter().fold((*first, *first), |(min, max), (_, p)| {
+                (
+                    Position::new(min.x.min(p.x), min.y.min(p.y)),
+                    Position::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            }),
+            None => return 1.0,
+        };
+        let (width, height) = (max.x - min.x, max.y - min.y);
+        let count = points.len() as f64;
+        // Points that lie on a line cover no area, but still need cells along it
+        ((width.max(1.0) * height.max(1.0) / count).sqrt())
+            .max(width.max(height) / count)
+            .max(1.0)
+    }
+
+    fn cell_of(&self, pos: &Position) -> Cell {
+        (
+            (pos.x / self.cell_size).floor() as i64,
+            (pos.y / self.cell_size).floor() as i64,
+        )
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    // Returns `true` if so many slots were added since the index was built
+    // that it is worth building it again
+    pub(crate) fn is_crowded(&self) -> bool {
+        self.len > REBUILD_GROWTH * self.built_len.max(16)
+    }
+
+    pub(crate) fn insert(&mut self, id: SlotId, pos: Position) {
+        let cell = self.cell_of(&pos);
+        self.cells.entry(cell).or_default().push((id, pos));
+        self.len += 1;
+        self.bounds = Some(match self.bounds {
+            Some((min, max)) => (
+                (min.0.min(cell.0), min.1.min(cell.1)),
+                (max.0.max(cell.0), max.1.max(cell.1)),
+            ),
+            None => (cell, cell),
+        });
+    }
+
+    pub(crate) fn remove(&mut self, id: SlotId, pos: &Position) {
+        let cell = self.cell_of(pos);
+        if let Some(points) = self.cells.get_mut(&cell) {
+            if let Some(i) = points.iter().position(|(point, _)| *point == id) {
+                points.swap_remove(i);
+                self.len -= 1;
+            }
+            if points.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    // Returns the slot closest to the position among the ones accepted by the `filter`,
+    // together with the distance. Slots that are equally close are told apart by
+    // their handles, so the result doesn't depend on the order of insertion.
+    pub(crate) fn nearest<F: FnMut(SlotId) -> bool>(
+        &self,
+        pos: &Position,
+        mut filter: F,
+    ) -> Option<(SlotId, f64)> {
+        let (min, max) = self.bounds?;
+        let center = self.cell_of(pos);
+        let last_ring = [
+            center.0 - min.0,
+            max.0 - center.0,
+            center.1 - min.1,
+            max.1 - center.1,
+        ]
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(0);
+
+        let mut best: Option<(SlotId, f64)> = None;
+        for ring in 0..=last_ring {
+            self.visit_ring(center, ring, (min, max), |points| {
+                for (id, point) in points {
+                    let distance = distance_between_positions(pos, point);
+                    let better = match best {
+                        Some((best_id, best_distance)) => {
+                            distance < best_distance || (distance == best_distance && *id < best_id)
+                        }
+                        None => true,
+                    };
+                    if better && filter(*id) {
+                        best = Some((*id, distance));
+                    }
+                }
+            });
+            // Slots in the further rings are at least that far away
+            if best.is_some_and(|(_, distance)| distance < ring as f64 * self.cell_size) {
+                break;
+            }
+        }
+        best
+    }
+
+    // Visits the cells that are `ring` cells away from the center, skipping the ones
+    // outside of the bounds, so sparse indices with tiny cells are searched quickly
+    fn visit_ring<F: FnMut(&[(SlotId, Position)])>(
+        &self,
+        center: Cell,
+        ring: i64,
+        (min, max): (Cell, Cell),
+        mut f: F,
+    ) {
+        let mut visit = |cell: Cell| {
+            if let Some(points) = self.cells.get(&cell) {
+                f(points);
+            }
+        };
+        if ring == 0 {
+            visit(center);
+            return;
+        }
+        let xs = (center.0 - ring).max(min.0)..=(center.0 + ring).min(max.0);
+        for y in [center.1 - ring, center.1 + ring].iter().copied() {
+            if y >= min.1 && y <= max.1 {
+                xs.clone().for_each(|x| visit((x, y)));
+            }
+        }
+        let ys = (center.1 - ring + 1).max(min.1)..=(center.1 + ring - 1).min(max.1);
+        for x in [center.0 - ring, center.0 + ring].iter().copied() {
+            if x >= min.0 && x <= max.0 {
+                ys.clone().for_each(|y| visit((x, y)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spatial::*;
+
+    fn id(index: usize) -> SlotId {
+        SlotId::from_parts(index, 0)
+    }
+
+    fn linear_nearest(points: &[(SlotId, Position)], pos: &Position) -> Option<SlotId> {
+        points
+            .iter()
+            .map(|(id, point)| (*id, distance_between_positions(pos, point)))
+            .min_by(|a, b| (a.1).partial_cmp(&b.1).unwrap())
+            .map(|(id, _)| id)
+    }
+
+    #[test]
+    fn nearest_matches_linear_scan() {
+        let points: Vec<(SlotId, Position)> = (0..500)
+            .map(|i| {
+                let x = ((i * 7919) % 1000) as f64;
+                let y = ((i * 104_729) % 700) as f64;
+                (id(i), Position::new(x, y))
+            })
+            .collect();
+        let index = SpatialIndex::build(points.clone());
+
+        for i in 0..100 {
+            let pos = Position::new((i * 37 % 1400) as f64 - 200.0, (i * 53 % 900) as f64);
+            assert_eq!(
+                index.nearest(&pos, |_| true).map(|(id, _)| id),
+                linear_nearest(&points, &pos)
+            );
+            assert_eq!(
+                index
+                    .nearest(&pos, |id| id.index() % 10 == 3)
+                    .map(|(id, _)| id),
+                linear_nearest(
+                    &points
+                        .iter()
+                        .filter(|(id, _)| id.index() % 10 == 3)
+                        .copied()
+                        .collect::<Vec<_>>(),
+                    &pos
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut index = SpatialIndex::build(vec![(id(0), Position::new(0.0, 0.0))]);
+        index.insert(id(1), Position::new(500.0, 500.0));
+        index.insert(id(2), Position::new(-500.0, 500.0));
+        assert_eq!(index.len(), 3);
+
+        let pos = Position::new(400.0, 400.0);
+        assert_eq!(index.nearest(&pos, |_| true).map(|(id, _)| id), Some(id(1)));
+        index.remove(id(1), &Position::new(500.0, 500.0));
+        assert_eq!(index.nearest(&pos, |_| true).map(|(id, _)| id), Some(id(0)));
+        assert_eq!(
+            index.nearest(&pos, |slot| slot != id(0)).map(|(id, _)| id),
+            Some(id(2))
+        );
+        assert_eq!(index.len(), 2);
+    }
+}
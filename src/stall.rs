@@ -0,0 +1,243 @@
+use std::fmt;
+use std::hash::Hash;
+
+use super::arena::*;
+use super::carrier::*;
+use super::event::*;
+use super::strategy::*;
+use super::TIME_EPSILON;
+
+// Seconds of simulated time without progress after which the swarm is considered stalled
+pub(crate) const DEFAULT_STALL_LIMIT: f64 = 1000.0;
+
+// Carrier that picked up the cargo or failed to find the target for it this many times
+// without any delivery is going in circles
+const CYCLING_ATTEMPTS: u32 = 2;
+
+// Measures the simulated time since the last delivery, so the limit doesn't depend
+// on the length of the step. Delivering the cargo into its target slot or dropping it
+// into the pit is the progress toward the target layout, while moving it between
+// the temporary slots is not.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct StallDetector {
+    pub(crate) limit: f64,
+    time: f64,
+    // Number of attempts of each carrier since the last progress
+    attempts: Vec<(CarrierId, u32)>,
+}
+
+impl Default for StallDetector {
+    fn default() -> Self {
+        StallDetector {
+            limit: DEFAULT_STALL_LIMIT,
+            time: 0.0,
+            attempts: Vec::new(),
+        }
+    }
+}
+
+impl StallDetector {
+    pub(crate) fn reset(&mut self) {
+        self.time = 0.0;
+        self.attempts.clear();
+    }
+
+    // Takes the events of a single step, `dt` seconds long, into account.
+    // Returns `true` at the step when the limit is reached.
+    pub(crate) fn observe<T>(&mut self, events: &[Event<T>], complete: bool, dt: f64) -> bool {
+        let progress = events
+            .iter()
+            .any(|event| matches!(event, Event::PutDown { .. } | Event::DroppedIntoPit { .. }));
+        if complete || progress {
+            self.reset();
+            return false;
+        }
+        for event in events {
+            let carrier = match *event {
+                Event::PickedUp { carrier, .. } | Event::Spawned { carrier, .. } => carrier,
+                Event::StateChanged {
+                    carrier,
+                    to: State::NOTARGET,
+                    ..
+                } => carrier,
+                _ => continue,
+            };
+            match self.attempts.iter_mut().find(|(id, _)| *id == carrier) {
+                Some((_, count)) => *count += 1,
+                None => self.attempts.push((carrier, 1)),
+            }
+        }
+        let stalled = self.is_stalled();
+        self.time += dt;
+        !stalled && self.is_stalled()
+    }
+
+    pub(crate) fn is_stalled(&self) -> bool {
+        self.time + TIME_EPSILON >= self.limit
+    }
+
+    pub(crate) fn seconds(&self) -> f64 {
+        self.time
+    }
+
+    pub(crate) fn cycling(&self) -> Vec<CarrierId> {
+        let mut cycling: Vec<CarrierId> = self
+            .attempts
+            .iter()
+            .filter(|&&(_, count)| count >= CYCLING_ATTEMPTS)
+            .map(|&(carrier, _)| carrier)
+            .collect();
+        cycling.sort();
+        cycling
+    }
+}
+
+/// Diagnostic of the swarm that made no progress toward the target layout
+/// for the number of seconds set with `Swarm::set_stall_limit_secs()`
+///
+/// Shortage and surplus are taken from the balance of the cargo: the layout needs more
+/// of the short cargo than there is, while there is more of the surplus cargo than needed.
+/// Without the spawner or the pit carriers can't fix that on their own. Both are listed
+/// in the order the cargo first appears in the slots.
+/// Cycling carriers keep picking up the cargo or looking for its target, but never deliver it.
+///
+/// # Example
+///
+/// ```
+/// use swarm_it::*;
+/// let mut game = Swarm::<char>::with_seed(1);
+/// game.set_stall_limit_secs(10.0);
+/// game.add_carrier(Carrier::new(0.0, 0.0));
+/// game.add_slot(Slot::new(100.0, 0.0, None, Some(Payload::new('A')), SlotKind::CLASSIC));
+/// for _ in 0..10 {
+///     game.tick().unwrap();
+/// }
+/// let report = game.get_stall_report().unwrap();
+/// assert_eq!(report.get_shortage(), [('A', 1)]);
+/// println!("{}", report);
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct StallReport<T> {
+    seconds: f64,
+    shortage: Vec<(T, u32)>,
+    surplus: Vec<(T, u32)>,
+    cycling: Vec<CarrierId>,
+}
+
+impl<T: PartialEq + Eq + Hash + Copy> StallReport<T> {
+    pub(crate) fn new(detector: &StallDetector, cargo_balance: &CargoBalance<T>) -> StallReport<T> {
+        let count = |sign: i32| {
+            cargo_balance
+                .iter()
+                .filter(|&(_, &balance)| balance.signum() == sign)
+                .map(|(&cargo, &balance)| (cargo, balance.unsigned_abs()))
+                .collect()
+        };
+        StallReport {
+            seconds: detector.seconds(),
+            shortage: count(-1),
+            surplus: count(1),
+            cycling: detector.cycling(),
+        }
+    }
+
+    /// Returns the seconds of simulated time since the last progress
+    pub fn get_seconds_without_progress(&self) -> f64 {
+        self.seconds
+    }
+
+    /// Returns the cargo that is missing, with the number of missing units
+    pub fn get_shortage(&self) -> &[(T, u32)] {
+        &self.shortage
+    }
+
+    /// Returns the cargo that is in excess, with the number of excessive units
+    pub fn get_surplus(&self) -> &[(T, u32)] {
+        &self.surplus
+    }
+
+    /// Returns the carriers that move the cargo around or look for its target without delivering it
+    pub fn get_cycling(&self) -> &[CarrierId] {
+        &self.cycling
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for StallReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no progress for {:.1} seconds", self.seconds)?;
+        for (name, cargo) in [("short", &self.shortage), ("surplus", &self.surplus)].iter() {
+            if !cargo.is_empty() {
+                write!(f, "; {}:", name)?;
+                for (cargo, count) in cargo.iter() {
+                    write!(f, " {}x{}", count, cargo)?;
+                }
+            }
+        }
+        if !self.cycling.is_empty() {
+            write!(f, "; cycling carriers:")?;
+            for carrier in &self.cycling {
+                write!(f, " {}", carrier)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stall::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn only_deliveries_are_progress() {
+        let carrier = CarrierId::from_parts(0, 0);
+        let slot = SlotId::from_parts(0, 0);
+        let mut detector = StallDetector {
+            limit: 3.0,
+            ..StallDetector::default()
+        };
+        let pickup = Event::PickedUp {
+            carrier,
+            slot,
+            cargo: 'A',
+        };
+        let drop = Event::TemporaryDrop {
+            carrier,
+            slot,
+            cargo: 'A',
+        };
+
+        assert!(!detector.observe(&[pickup], false, 1.0));
+        assert!(!detector.observe(&[drop, pickup], false, 1.0));
+        assert!(detector.observe::<char>(&[], false, 1.0));
+        assert!(detector.is_stalled());
+        assert_abs_diff_eq!(detector.seconds(), 3.0);
+        assert_eq!(detector.cycling(), vec![carrier]);
+        assert!(!detector.observe::<char>(&[], false, 1.0));
+
+        let delivery = Event::PutDown {
+            carrier,
+            slot,
+            cargo: 'A',
+        };
+        assert!(!detector.observe(&[delivery], false, 1.0));
+        assert!(!detector.is_stalled());
+        assert!(detector.cycling().is_empty());
+    }
+
+    #[test]
+    fn limit_is_measured_in_simulated_time() {
+        let mut detector = StallDetector {
+            limit: 3.0,
+            ..StallDetector::default()
+        };
+
+        for _ in 0..299 {
+            assert!(!detector.observe::<char>(&[], false, 0.01));
+        }
+        assert_abs_diff_eq!(detector.seconds(), 2.99, epsilon = 1e-9);
+        assert!(detector.observe::<char>(&[], false, 0.01));
+        assert_abs_diff_eq!(detector.seconds(), 3.0, epsilon = 1e-9);
+    }
+}
@@ -0,0 +1,403 @@
+use std::cmp::Ordering;
+use std::hash::Hash;
+
+use indexmap::IndexMap;
+
+use super::arena::*;
+use super::assignment;
+use super::carrier::*;
+use super::dispatcher::*;
+use super::payload::*;
+use super::position::*;
+use super::slot::*;
+
+/// Amount of cargo of each kind that is in excess (positive) or missing (negative).
+/// Cargo is kept in the order it was first counted, so the decisions that depend on the order
+/// are the same in each run, also after the swarm is restored or replayed.
+pub type CargoBalance<T> = IndexMap<T, i32>;
+
+const UNREACHABLE_COST: f64 = 1e12;
+
+/// Describes the job that is given to the idle carrier
+///
+/// Task     | Meaning
+/// ---------|--------
+/// ToPit    | Pick up the payload from `slot` and drop it into the `pit`
+/// Transfer | Pick up the payload from `slot` and bring it to the `target`. If there is no target yet, it will be looked for once the payload is picked up
+/// Spawn    | Go to the closest spawner and bring the `cargo` it produces to the slot that needs it
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Task<T> {
+    ToPit {
+        slot: SlotId,
+        pit: SlotId,
+    },
+    Transfer {
+        slot: SlotId,
+        target: Option<SlotId>,
+    },
+    Spawn {
+        cargo: T,
+    },
+}
+
+/// Represents the decision of the `DispatchStrategy`: the `carrier`
+/// should perform the `task`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Assignment<T> {
+    pub carrier: CarrierId,
+    pub task: Task<T>,
+}
+
+/// Read-only view of the swarm that is passed to the `DispatchStrategy`
+pub struct DispatchContext<'a, T: PartialEq + Eq + Hash + Copy> {
+    carriers: &'a Carriers<T>,
+    slots: &'a Slots<T>,
+    dispatcher: &'a Dispatcher<T>,
+}
+
+impl<'a, T: PartialEq + Eq + Hash + Copy> DispatchContext<'a, T> {
+    pub(crate) fn new(
+        carriers: &'a Carriers<T>,
+        slots: &'a Slots<T>,
+        dispatcher: &'a Dispatcher<T>,
+    ) -> Self {
+        DispatchContext {
+            carriers,
+            slots,
+            dispatcher,
+        }
+    }
+
+    /// Returns all carriers
+    pub fn carriers(&self) -> &Arena<CarrierId, Carrier<T>> {
+        self.carriers
+    }
+
+    /// Returns all slots
+    pub fn slots(&self) -> &Arena<SlotId, Slot<T>> {
+        self.slots
+    }
+
+    /// Returns handles of the carriers that are waiting for a task
+    pub fn idle_carriers(&self) -> Vec<CarrierId> {
+        self.carriers
+            .iter()
+            .filter(|(_, carrier)| carrier.state.is_idle())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Returns the cargo balance. Positive value means that there is more cargo
+    /// of given kind than needed, negative value means that some cargo is missing.
+    /// Cargo that is in balance is not present.
+    pub fn cargo_balance(&self) -> &CargoBalance<T> {
+        &self.dispatcher.cargo_balance
+    }
+
+    /// Returns the distance between two slots
+    pub fn distance_slot_slot(&self, s1: SlotId, s2: SlotId) -> f64 {
+        self.dispatcher.get_distance_slot_slot(self.slots, s1, s2)
+    }
+
+    /// Returns the distance between slot and the position
+    pub fn distance_slot_position(&self, slot: SlotId, pos: &Position) -> f64 {
+        self.dispatcher
+            .get_distance_slot_position(self.slots, slot, pos)
+    }
+
+    /// Returns the handle of the slot closest to the given position among
+    /// the slots accepted by the `classifier`. Slots that carriers can't get to are skipped.
+    pub fn find_closest_slot(
+        &self,
+        pos: &Position,
+        classifier: fn(&Slot<T>) -> bool,
+    ) -> Option<SlotId> {
+        self.dispatcher
+            .find_closest_object(self.slots, pos, classifier)
+    }
+
+    /// Returns the handle of the slot of given `kind` closest to the given position.
+    /// Unlike `find_closest_slot()` it looks only at the slots of that kind, so it
+    /// stays fast when there are few pits or spawners among many slots.
+    pub fn find_closest_slot_of_kind(&self, pos: &Position, kind: SlotKind) -> Option<SlotId> {
+        self.dispatcher
+            .find_closest_of_kind(self.slots, pos, kind, |_| true)
+    }
+
+    /// Returns the planner that produces tasks according to the default policy
+    pub fn planner(&self) -> TaskPlanner<'_, T> {
+        TaskPlanner {
+            context: self,
+            reserved: Reservations::new(),
+        }
+    }
+}
+
+/// Produces consecutive tasks according to the default policy: pit first,
+/// then mismatched payload with free target, then any mismatched payload,
+/// then spawner. Within each of these, slots with higher priority and earlier
+/// deadline are served first (see `Slot::set_priority()` and `Slot::set_deadline()`).
+/// Planned tasks are remembered, so the same task is never returned twice.
+pub struct TaskPlanner<'a, T: PartialEq + Eq + Hash + Copy> {
+    context: &'a DispatchContext<'a, T>,
+    reserved: Reservations<T>,
+}
+
+impl<'a, T: PartialEq + Eq + Hash + Copy> TaskPlanner<'a, T> {
+    /// Returns the next task or `None` if there is nothing more to do
+    pub fn next_task(&mut self) -> Option<Task<T>> {
+        let dispatcher = self.context.dispatcher;
+        let slots = self.context.slots;
+        let reserved = &mut self.reserved;
+        if let Some((slot_index, cargo)) =
+            dispatcher.find_slot_with_payload_that_should_go_to_the_pit(slots, reserved)
+        {
+            let pit_index = dispatcher.find_closest_of_kind(
+                slots,
+                slots[slot_index].get_position(),
+                SlotKind::PIT,
+                |_| true,
+            )?;
+            reserved.change_cargo_balance(cargo, -1);
+            reserved.reserve(slot_index);
+            Some(Task::ToPit {
+                slot: slot_index,
+                pit: pit_index,
+            })
+        } else if let Some((slot_index, possible_target)) =
+            dispatcher.find_slot_with_mismatched_payload_and_free_target(slots, reserved)
+        {
+            reserved.reserve(slot_index);
+            reserved.reserve(possible_target);
+            Some(Task::Transfer {
+                slot: slot_index,
+                target: Some(possible_target),
+            })
+        } else if let Some(slot_index) =
+            dispatcher.find_slot_with_mismatched_payload(slots, reserved)
+        {
+            reserved.reserve(slot_index);
+            Some(Task::Transfer {
+                slot: slot_index,
+                target: None,
+            })
+        } else if slots.values().any(|slot| slot.is_spawner()) {
+            let cargo = dispatcher.find_missing_cargo(slots, reserved)?;
+            reserved.change_cargo_balance(cargo, 1);
+            Some(Task::Spawn { cargo })
+        } else {
+            None
+        }
+    }
+}
+
+/// Strategy that decides which idle carrier should perform which task.
+/// It is consulted once per tick, and only if there are idle carriers.
+///
+/// Carriers that are already busy are driven by the swarm itself, the strategy
+/// only hands out new tasks. Returned assignments are validated and invalid ones
+/// (busy carrier, slot already taken care of, etc.) are reported as `SwarmError::InvalidAssignment`.
+///
+/// # Example
+///
+/// ```
+/// use swarm_it::*;
+///
+/// // Only the first idle carrier is allowed to work
+/// struct Lonely;
+///
+/// impl DispatchStrategy<char> for Lonely {
+///     fn assign(&mut self, context: &DispatchContext<char>) -> Vec<Assignment<char>> {
+///         let mut planner = context.planner();
+///         match (context.idle_carriers().first(), planner.next_task()) {
+///             (Some(&carrier), Some(task)) => vec![Assignment { carrier, task }],
+///             _ => Vec::new(),
+///         }
+///     }
+/// }
+///
+/// let game = Swarm::<char>::with_strategy(Lonely);
+/// ```
+pub trait DispatchStrategy<T: PartialEq + Eq + Hash + Copy> {
+    fn assign(&mut self, context: &DispatchContext<T>) -> Vec<Assignment<T>>;
+}
+
+/// Default strategy. Each idle carrier, in order, takes the next task
+/// returned by the `TaskPlanner`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GreedyStrategy;
+
+impl<T: PartialEq + Eq + Hash + Copy> DispatchStrategy<T> for GreedyStrategy {
+    fn assign(&mut self, context: &DispatchContext<T>) -> Vec<Assignment<T>> {
+        let mut planner = context.planner();
+        let mut assignments = Vec::new();
+        for carrier in context.idle_carriers() {
+            match planner.next_task() {
+                Some(task) => assignments.push(Assignment { carrier, task }),
+                None => break,
+            }
+        }
+        assignments
+    }
+}
+
+/// Gathers all open tasks and matches them with the idle carriers
+/// so the total distance to travel is minimal (Hungarian algorithm).
+/// Tasks are matched in tiers of urgency: the tasks of the slots with the highest priority
+/// and the earliest deadline get their carriers first, the rest are matched with the carriers
+/// that are left (see `Slot::set_priority()` and `Slot::set_deadline()`).
+/// Every task returned by the `TaskPlanner` is taken into account, so the time
+/// grows with the number of mismatched slots. Prefer `GreedyStrategy` for the huge layouts.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OptimalStrategy;
+
+impl OptimalStrategy {
+    // Slot the spawned cargo will most likely be brought to
+    fn spawn_target<T: PartialEq + Eq + Hash + Copy>(
+        context: &DispatchContext<T>,
+        task: &Task<T>,
+    ) -> Option<SlotId> {
+        match *task {
+            Task::Spawn { cargo } => context
+                .dispatcher
+                .find_slot_for_target(context.slots, Some(Payload::new(cargo))),
+            _ => None,
+        }
+    }
+
+    // Slot whose priority and deadline tell how urgent the task is
+    fn served_slot<T>(task: &Task<T>, spawn_target: Option<SlotId>) -> Option<SlotId> {
+        match *task {
+            Task::ToPit { slot, .. } => Some(slot),
+            Task::Transfer { slot, target } => Some(target.unwrap_or(slot)),
+            Task::Spawn { .. } => spawn_target,
+        }
+    }
+
+    // Ordering of the tasks from the most urgent one, tasks that serve no slot last
+    fn cmp_urgency<T: PartialEq + Eq + Hash + Copy>(
+        context: &DispatchContext<T>,
+        a: Option<SlotId>,
+        b: Option<SlotId>,
+    ) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => context.slots[a].cmp_urgency(&context.slots[b]),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    fn task_cost<T: PartialEq + Eq + Hash + Copy>(
+        context: &DispatchContext<T>,
+        pos: &Position,
+        task: &Task<T>,
+        spawn_target: Option<SlotId>,
+    ) -> f64 {
+        let cost = match *task {
+            Task::ToPit { slot, pit } => {
+                context.distance_slot_position(slot, pos) + context.distance_slot_slot(slot, pit)
+            }
+            Task::Transfer { slot, target } => {
+                context.distance_slot_position(slot, pos)
+                    + target.map_or(0.0, |target| context.distance_slot_slot(slot, target))
+            }
+            Task::Spawn { .. } => context
+                .find_closest_slot_of_kind(pos, SlotKind::SPAWNER)
+                .map_or(0.0, |spawner| {
+                    context.distance_slot_position(spawner, pos)
+                        + spawn_target
+                            .map_or(0.0, |target| context.distance_slot_slot(spawner, target))
+                }),
+        };
+        // Slots walled off by the obstacles are infinitely far away
+        if cost.is_finite() {
+            cost
+        } else {
+            UNREACHABLE_COST
+        }
+    }
+
+    // Matches the tasks with the carriers, so the total distance to travel is minimal
+    fn match_by_distance<T: PartialEq + Eq + Hash + Copy>(
+        context: &DispatchContext<T>,
+        idle: &[CarrierId],
+        tasks: &[(Task<T>, Option<SlotId>)],
+    ) -> Vec<Assignment<T>> {
+        let cost = |task: usize, carrier: usize| {
+            let (task, spawn_target) = &tasks[task];
+            OptimalStrategy::task_cost(
+                context,
+                context.carriers()[idle[carrier]].get_position(),
+                task,
+                *spawn_target,
+            )
+        };
+
+        // Solver needs at most as many rows as columns, so the shorter list goes into the rows
+        if tasks.len() <= idle.len() {
+            let costs: Vec<Vec<f64>> = (0..tasks.len())
+                .map(|task| (0..idle.len()).map(|carrier| cost(task, carrier)).collect())
+                .collect();
+            assignment::solve(&costs)
+                .into_iter()
+                .enumerate()
+                .map(|(task, carrier)| Assignment {
+                    carrier: idle[carrier],
+                    task: tasks[task].0,
+                })
+                .collect()
+        } else {
+            let costs: Vec<Vec<f64>> = (0..idle.len())
+                .map(|carrier| (0..tasks.len()).map(|task| cost(task, carrier)).collect())
+                .collect();
+            assignment::solve(&costs)
+                .into_iter()
+                .enumerate()
+                .map(|(carrier, task)| Assignment {
+                    carrier: idle[carrier],
+                    task: tasks[task].0,
+                })
+                .collect()
+        }
+    }
+}
+
+impl<T: PartialEq + Eq + Hash + Copy> DispatchStrategy<T> for OptimalStrategy {
+    fn assign(&mut self, context: &DispatchContext<T>) -> Vec<Assignment<T>> {
+        let mut idle = context.idle_carriers();
+        if idle.is_empty() {
+            return Vec::new();
+        }
+        let mut planner = context.planner();
+        let mut tasks: Vec<(Task<T>, Option<SlotId>)> = std::iter::from_fn(|| planner.next_task())
+            .map(|task| {
+                let spawn_target = OptimalStrategy::spawn_target(context, &task);
+                (task, spawn_target)
+            })
+            .collect();
+        // Sorting is stable, so the tasks of the same urgency stay in the order of the planner
+        let urgency = |(task, spawn_target): &(Task<T>, Option<SlotId>)| {
+            OptimalStrategy::served_slot(task, *spawn_target)
+        };
+        tasks.sort_by(|a, b| OptimalStrategy::cmp_urgency(context, urgency(a), urgency(b)));
+
+        let mut assignments = Vec::new();
+        let mut start = 0;
+        while start < tasks.len() && !idle.is_empty() {
+            let tier = tasks[start..]
+                .iter()
+                .take_while(|task| {
+                    OptimalStrategy::cmp_urgency(context, urgency(&tasks[start]), urgency(task))
+                        == Ordering::Equal
+                })
+                .count();
+            let matched =
+                OptimalStrategy::match_by_distance(context, &idle, &tasks[start..start + tier]);
+            idle.retain(|&carrier| !matched.iter().any(|a| a.carrier == carrier));
+            assignments.extend(matched);
+            start += tier;
+        }
+        assignments
+    }
+}
@@ -0,0 +1,292 @@
+use std::error::Error;
+use std::fmt;
+
+use super::arena::*;
+use super::payload::*;
+use super::position::*;
+use super::slot::*;
+use super::Swarm;
+
+const DEFAULT_PIT_GLYPH: char = '#';
+const DEFAULT_SPAWNER_GLYPH: char = '*';
+const DEFAULT_EMPTY_GLYPH: char = ' ';
+
+/// Error reported when the text grids can't be turned into slots
+///
+/// Variant  | Meaning
+/// ---------|--------
+/// Conflict | Cell at given `column` and `row` is a pit or a spawner in one grid, but holds a different character in the other
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TextLayoutError {
+    Conflict { column: usize, row: usize },
+}
+
+impl fmt::Display for TextLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TextLayoutError::Conflict { column, row } => write!(
+                f,
+                "cell at column {}, row {} is a pit or spawner in one grid but not the same in the other",
+                column, row
+            ),
+        }
+    }
+}
+
+impl Error for TextLayoutError {}
+
+/// Turns two text grids, the current text and the target text, into slots for `Swarm<char>`.
+/// Each character becomes a slot, placed `spacing` units apart from its neighbours. Character
+/// of the current text is the payload of the slot and character of the target text at the
+/// same place is its target.
+///
+/// Glyph   | Meaning
+/// --------|--------
+/// ' '     | Empty cell, no slot is created if the cell is empty in both grids
+/// '#'     | Pit
+/// '*'     | Spawner
+///
+/// Pit or spawner may be put in one grid only, the same cell of the other grid must be then empty.
+/// Glyphs can be changed, so these characters can be used in the texts as well.
+///
+/// # Example
+///
+/// ```
+/// use swarm_it::*;
+/// let mut game = Swarm::<char>::new();
+/// let text = TextLayout::new(20.0);
+/// game.add_text_layout(&text, "RUST   #", "TRUST *").unwrap();
+/// assert_eq!(game.get_slots().len(), 7);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct TextLayout {
+    origin: Position,
+    spacing: f64,
+    pit: char,
+    spawner: char,
+    empty: char,
+}
+
+impl TextLayout {
+    /// Creates the text layout with the characters `spacing` units apart,
+    /// starting at (0, 0) and with the default glyphs
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let text = swarm_it::TextLayout::new(40.0);
+    /// ```
+    pub fn new(spacing: f64) -> TextLayout {
+        TextLayout {
+            origin: Position::new(0.0, 0.0),
+            spacing,
+            pit: DEFAULT_PIT_GLYPH,
+            spawner: DEFAULT_SPAWNER_GLYPH,
+            empty: DEFAULT_EMPTY_GLYPH,
+        }
+    }
+
+    /// Sets the position of the first character of the first line.
+    /// Following characters go to the right and following lines go down.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut text = swarm_it::TextLayout::new(40.0);
+    /// text.set_origin(100.0, 50.0);
+    /// let slots = text.slots("A", "B").unwrap();
+    /// approx::assert_abs_diff_eq!(slots[0].get_position().x, 100.0);
+    /// ```
+    pub fn set_origin(&mut self, x: f64, y: f64) {
+        self.origin = Position::new(x, y);
+    }
+
+    /// Sets the character that stands for the pit
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut text = swarm_it::TextLayout::new(40.0);
+    /// text.set_pit_glyph('P');
+    /// assert!(text.slots("P", "").unwrap()[0].is_pit());
+    /// ```
+    pub fn set_pit_glyph(&mut self, glyph: char) {
+        self.pit = glyph;
+    }
+
+    /// Sets the character that stands for the spawner
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut text = swarm_it::TextLayout::new(40.0);
+    /// text.set_spawner_glyph('S');
+    /// assert!(text.slots("", "S").unwrap()[0].is_spawner());
+    /// ```
+    pub fn set_spawner_glyph(&mut self, glyph: char) {
+        self.spawner = glyph;
+    }
+
+    /// Sets the character that stands for the empty cell
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut text = swarm_it::TextLayout::new(40.0);
+    /// text.set_empty_glyph('.');
+    /// assert_eq!(text.slots("A.", "..").unwrap().len(), 1);
+    /// ```
+    pub fn set_empty_glyph(&mut self, glyph: char) {
+        self.empty = glyph;
+    }
+
+    fn kind_of(&self, glyph: char) -> Option<SlotKind> {
+        if glyph == self.pit {
+            Some(SlotKind::PIT)
+        } else if glyph == self.spawner {
+            Some(SlotKind::SPAWNER)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the slots described by the `current` and the `target` text,
+    /// line by line, from left to right
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let text = swarm_it::TextLayout::new(40.0);
+    /// let slots = text.slots("AB\n C", "BA\nC ").unwrap();
+    /// assert_eq!(slots.len(), 4);
+    /// assert_eq!(slots[3].get_payloads(), [Some(swarm_it::Payload::new('C')), None]);
+    /// ```
+    pub fn slots(&self, current: &str, target: &str) -> Result<Vec<Slot<char>>, TextLayoutError> {
+        let current: Vec<Vec<char>> = current.lines().map(|l| l.chars().collect()).collect();
+        let target: Vec<Vec<char>> = target.lines().map(|l| l.chars().collect()).collect();
+        let glyph = |grid: &[Vec<char>], column: usize, row: usize| {
+            grid.get(row)
+                .and_then(|line| line.get(column))
+                .copied()
+                .unwrap_or(self.empty)
+        };
+
+        let mut slots = Vec::new();
+        for row in 0..current.len().max(target.len()) {
+            let width = [&current, &target]
+                .iter()
+                .map(|grid| grid.get(row).map_or(0, |line| line.len()))
+                .max()
+                .unwrap_or(0);
+            for column in 0..width {
+                let x = self.origin.x + column as f64 * self.spacing;
+                let y = self.origin.y + row as f64 * self.spacing;
+                let (c, t) = (glyph(&current, column, row), glyph(&target, column, row));
+                let slot = match (self.kind_of(c), self.kind_of(t)) {
+                    (Some(kind), None) if t == self.empty => Slot::new(x, y, None, None, kind),
+                    (None, Some(kind)) if c == self.empty => Slot::new(x, y, None, None, kind),
+                    (Some(kind), Some(other)) if kind == other => Slot::new(x, y, None, None, kind),
+                    (None, None) if c == self.empty && t == self.empty => continue,
+                    (None, None) => {
+                        let payload = |g| Some(Payload::new(g)).filter(|_| g != self.empty);
+                        Slot::new(x, y, payload(c), payload(t), SlotKind::CLASSIC)
+                    }
+                    _ => return Err(TextLayoutError::Conflict { column, row }),
+                };
+                slots.push(slot);
+            }
+        }
+        Ok(slots)
+    }
+}
+
+impl Swarm<char> {
+    /// Adds the slots described by the `current` and the `target` text, see `TextLayout`.
+    /// Returns the handles of the slots, in the same order as `TextLayout::slots()`.
+    /// Nothing is added if the texts are in conflict.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// let ids = game
+    ///     .add_text_layout(&TextLayout::new(20.0), "SWARM", "WARMS")
+    ///     .unwrap();
+    /// assert_eq!(ids.len(), 5);
+    /// ```
+    pub fn add_text_layout(
+        &mut self,
+        text: &TextLayout,
+        current: &str,
+        target: &str,
+    ) -> Result<Vec<SlotId>, TextLayoutError> {
+        Ok(text
+            .slots(current, target)?
+            .into_iter()
+            .map(|slot| self.add_slot(slot))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::text::*;
+
+    #[test]
+    fn grids_of_different_size() {
+        let text = TextLayout::new(10.0);
+        let slots = text.slots("AB\nC", "B\n\nDE").unwrap();
+
+        let positions: Vec<(f64, f64)> = slots
+            .iter()
+            .map(|slot| (slot.get_position().x, slot.get_position().y))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (0.0, 10.0),
+                (0.0, 20.0),
+                (10.0, 20.0)
+            ]
+        );
+
+        let p = |c| Some(Payload::new(c));
+        let payloads: Vec<[Option<Payload<char>>; 2]> =
+            slots.iter().map(|slot| slot.get_payloads()).collect();
+        assert_eq!(
+            payloads,
+            vec![
+                [p('A'), p('B')],
+                [p('B'), None],
+                [p('C'), None],
+                [None, p('D')],
+                [None, p('E')],
+            ]
+        );
+    }
+
+    #[test]
+    fn special_glyphs() {
+        let text = TextLayout::new(10.0);
+        let slots = text.slots("#A *", "# B").unwrap();
+        assert_eq!(slots.len(), 4);
+        assert!(slots[0].is_pit());
+        assert!(slots[3].is_spawner());
+
+        assert_eq!(
+            text.slots("#A", "BA").err(),
+            Some(TextLayoutError::Conflict { column: 0, row: 0 })
+        );
+        assert_eq!(
+            TextLayoutError::Conflict { column: 0, row: 0 }.to_string(),
+            "cell at column 0, row 0 is a pit or spawner in one grid but not the same in the other"
+        );
+        assert_eq!(
+            text.slots("A\n#", "A\n*").err(),
+            Some(TextLayoutError::Conflict { column: 0, row: 1 })
+        );
+    }
+}
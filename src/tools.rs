@@ -1,5 +1,76 @@
+use super::arena::*;
+use super::error::*;
 use super::position::*;
+use super::slot::*;
+
+use std::hash::Hash;
+
+// Random number generator owned by the swarm, seedable for reproducible runs
+pub(crate) type SwarmRng = rand_pcg::Pcg32;
+
+// FNV-1a hasher. Unlike `DefaultHasher` it gives the same hash in every build and on every platform.
+#[cfg(any(feature = "render", feature = "replay"))]
+pub(crate) struct StableHasher(u64);
+
+#[cfg(any(feature = "render", feature = "replay"))]
+impl StableHasher {
+    pub(crate) fn new() -> StableHasher {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+#[cfg(any(feature = "render", feature = "replay"))]
+impl std::hash::Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // Integers are hashed in the same byte order and width on every platform
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
 
 pub(crate) fn distance_between_positions(p1: &Position, p2: &Position) -> f64 {
     ((p1.x - p2.x) * (p1.x - p2.x) + (p1.y - p2.y) * (p1.y - p2.y)).sqrt()
 }
+
+// Returns the signed angle, in range (-π, π], by which `from` must be rotated to reach `to`
+pub(crate) fn angle_difference(to: f64, from: f64) -> f64 {
+    let full = std::f64::consts::PI * 2.0;
+    let mut difference = (to - from) % full;
+    if difference > std::f64::consts::PI {
+        difference -= full;
+    } else if difference <= -std::f64::consts::PI {
+        difference += full;
+    }
+    difference
+}
+
+pub(crate) fn slot_at<T: PartialEq + Eq + Hash + Copy>(
+    slots: &mut Slots<T>,
+    carrier: CarrierId,
+    slot: SlotId,
+) -> Result<&mut Slot<T>, SwarmError> {
+    slots
+        .get_mut(slot)
+        .ok_or(SwarmError::StaleSlot { carrier, slot })
+}
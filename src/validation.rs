@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use super::arena::*;
+use super::Swarm;
+
+/// Problems that prevent the swarm from reaching the target layout, see `Swarm::validate()`
+///
+/// Variant                | Meaning
+/// -----------------------|--------
+/// NoCarriers             | There are no carriers to do the job
+/// TargetOnSpecialSlot    | Pit or spawner `slot` has the target payload, which can never be satisfied
+/// OverlappingSlots       | Slots `first` and `second` are at the same position
+/// SurplusWithoutPit      | There is more `cargo` than the target layout needs, but no pit to drop it into
+/// ShortageWithoutSpawner | There is less `cargo` than the target layout needs, but no spawner to bring it from
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ValidationError<T> {
+    NoCarriers,
+    TargetOnSpecialSlot { slot: SlotId },
+    OverlappingSlots { first: SlotId, second: SlotId },
+    SurplusWithoutPit { cargo: T, count: u32 },
+    ShortageWithoutSpawner { cargo: T, count: u32 },
+}
+
+impl<T: fmt::Display> fmt::Display for ValidationError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::NoCarriers => write!(f, "there are no carriers"),
+            ValidationError::TargetOnSpecialSlot { slot } => write!(
+                f,
+                "slot {} is a pit or a spawner, but has the target payload",
+                slot
+            ),
+            ValidationError::OverlappingSlots { first, second } => {
+                write!(f, "slots {} and {} are at the same position", first, second)
+            }
+            ValidationError::SurplusWithoutPit { cargo, count } => write!(
+                f,
+                "{} unit(s) of cargo {} are not needed, but there is no pit",
+                count, cargo
+            ),
+            ValidationError::ShortageWithoutSpawner { cargo, count } => write!(
+                f,
+                "{} unit(s) of cargo {} are missing, but there is no spawner",
+                count, cargo
+            ),
+        }
+    }
+}
+
+impl<T: fmt::Debug + fmt::Display> Error for ValidationError<T> {}
+
+impl<T: PartialEq + Eq + Hash + Copy> Swarm<T> {
+    /// Checks whether the target layout can be reached at all. Returns all the problems found,
+    /// in the order of the `ValidationError` variants. Problems of the same kind are listed
+    /// in the order of the slots, and the cargo in the order it first appears in the slots.
+    /// Swarm that fails the validation
+    /// will never finish the job, see also `get_stall_report()`.
+    ///
+    /// Cargo is counted in the slots and in the carriers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use swarm_it::*;
+    /// let mut game = Swarm::<char>::new();
+    /// game.add_slot(Slot::new(100.0, 100.0, Some(Payload::new('A')), None, SlotKind::CLASSIC));
+    /// assert_eq!(
+    ///     game.validate(),
+    ///     Err(vec![
+    ///         ValidationError::NoCarriers,
+    ///         ValidationError::SurplusWithoutPit { cargo: 'A', count: 1 }
+    ///     ])
+    /// );
+    ///
+    /// game.add_carrier(Carrier::new(0.0, 0.0));
+    /// game.add_slot(make_slot_pit!(200.0, 100.0));
+    /// assert_eq!(game.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<ValidationError<T>>> {
+        let mut errors = Vec::new();
+        if self.carriers.is_empty() {
+            errors.push(ValidationError::NoCarriers);
+        }
+
+        let mut positions = HashMap::new();
+        let (mut pit, mut spawner) = (false, false);
+        let mut overlapping = Vec::new();
+        for (id, slot) in self.slots.iter() {
+            pit |= slot.is_pit();
+            spawner |= slot.is_spawner();
+            if (slot.is_pit() || slot.is_spawner()) && !slot.get_targets().is_empty() {
+                errors.push(ValidationError::TargetOnSpecialSlot { slot: id });
+            }
+            // Adding zero turns -0.0 into 0.0, so both are the same position
+            let position = slot.get_position();
+            let key = ((position.x + 0.0).to_bits(), (position.y + 0.0).to_bits());
+            if let Some(&first) = positions.get(&key) {
+                overlapping.push(ValidationError::OverlappingSlots { first, second: id });
+            } else {
+                positions.insert(key, id);
+            }
+        }
+        errors.append(&mut overlapping);
+
+        let cargo_balance = self.count_cargo();
+        if !pit {
+            errors.extend(
+                cargo_balance
+                    .iter()
+                    .filter(|&(_, &balance)| balance > 0)
+                    .map(|(&cargo, &balance)| ValidationError::SurplusWithoutPit {
+                        cargo,
+                        count: balance.unsigned_abs(),
+                    }),
+            );
+        }
+        if !spawner {
+            errors.extend(
+                cargo_balance
+                    .iter()
+                    .filter(|&(_, &balance)| balance < 0)
+                    .map(
+                        |(&cargo, &balance)| ValidationError::ShortageWithoutSpawner {
+                            cargo,
+                            count: balance.unsigned_abs(),
+                        },
+                    ),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn all_problems_are_reported() {
+        let mut game = Swarm::<char>::new();
+        let first = game.add_slot(Slot::new(
+            100.0,
+            100.0,
+            Some(Payload::new('A')),
+            Some(Payload::new('B')),
+            SlotKind::CLASSIC,
+        ));
+        let second = game.add_slot(Slot::new(
+            100.0,
+            100.0,
+            None,
+            Some(Payload::new('A')),
+            SlotKind::CLASSIC,
+        ));
+        let pit = game.add_slot(Slot::new(
+            -0.0,
+            0.0,
+            None,
+            Some(Payload::new('C')),
+            SlotKind::PIT,
+        ));
+        let spawner = game.add_slot(make_slot_spawner!(0.0, 0.0));
+        assert_eq!(
+            game.validate(),
+            Err(vec![
+                ValidationError::NoCarriers,
+                ValidationError::TargetOnSpecialSlot { slot: pit },
+                ValidationError::OverlappingSlots { first, second },
+                ValidationError::OverlappingSlots {
+                    first: pit,
+                    second: spawner
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn cargo_must_be_balanced_without_pit_and_spawner() {
+        let mut game = Swarm::<char>::new();
+        let mut carrier = Carrier::new(0.0, 0.0);
+        carrier.set_capacity(2);
+        game.add_carrier(carrier);
+        game.add_slot(Slot::new_bin(
+            100.0,
+            100.0,
+            3,
+            vec!['A', 'A', 'B'],
+            vec!['B', 'B', 'B'],
+        ));
+        game.add_slot(Slot::new(
+            100.0,
+            200.0,
+            Some(Payload::new('D')),
+            Some(Payload::new('C')),
+            SlotKind::CLASSIC,
+        ));
+        assert_eq!(
+            game.validate(),
+            Err(vec![
+                ValidationError::SurplusWithoutPit {
+                    cargo: 'A',
+                    count: 2
+                },
+                ValidationError::SurplusWithoutPit {
+                    cargo: 'D',
+                    count: 1
+                },
+                ValidationError::ShortageWithoutSpawner {
+                    cargo: 'B',
+                    count: 2
+                },
+                ValidationError::ShortageWithoutSpawner {
+                    cargo: 'C',
+                    count: 1
+                },
+            ])
+        );
+
+        game.add_slot(make_slot_pit!(200.0, 100.0));
+        game.add_slot(make_slot_spawner!(300.0, 100.0));
+        assert_eq!(game.validate(), Ok(()));
+    }
+}
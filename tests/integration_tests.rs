@@ -1,12 +1,12 @@
 #[macro_use(make_slot_pit, make_slot_spawner)]
 extern crate swarm_it;
-use swarm_it::{Carrier, Payload, Slot, SlotKind, State};
+use swarm_it::{Carrier, Event, ObstacleGrid, Payload, Slot, SlotKind, State, SwarmError};
 
 #[test]
 fn conduct_to_targetting() {
     let mut game = swarm_it::Swarm::new();
 
-    game.add_carrier(Carrier::new(0.0, 0.0));
+    let first = game.add_carrier(Carrier::new(0.0, 0.0));
     game.add_carrier(Carrier::new(0.0, 0.0));
     game.add_slot(Slot::new(
         100.0,
@@ -23,10 +23,10 @@ fn conduct_to_targetting() {
         SlotKind::CLASSIC,
     ));
 
-    game.tick();
+    game.tick().unwrap();
 
     // Carrier should have target set to slot with 'X'
-    let state = game.get_carriers()[0].get_state();
+    let state = game.get_carriers()[first].get_state();
     if let State::TARGETING(target) = state {
         assert_eq!(
             game.get_slots()[target].get_payloads()[0],
@@ -58,10 +58,10 @@ fn idle_carriers_reporting() {
         SlotKind::CLASSIC,
     ));
 
-    let mut all_carriers_idle = game.tick();
+    let mut all_carriers_idle = game.tick().unwrap().is_finished();
     assert!(!all_carriers_idle);
     for _ in 1..100 {
-        all_carriers_idle = game.tick();
+        all_carriers_idle = game.tick().unwrap().is_finished();
     }
     assert!(all_carriers_idle);
 }
@@ -70,8 +70,8 @@ fn idle_carriers_reporting() {
 fn parallel_transfer_to_pit() {
     let mut game = swarm_it::Swarm::new();
 
-    game.add_carrier(Carrier::new(50.0, 50.0));
-    game.add_carrier(Carrier::new(50.0, 50.0));
+    let c1 = game.add_carrier(Carrier::new(50.0, 50.0));
+    let c2 = game.add_carrier(Carrier::new(50.0, 50.0));
     game.add_slot(Slot::new(
         200.0,
         200.0,
@@ -86,14 +86,14 @@ fn parallel_transfer_to_pit() {
         None,
         SlotKind::CLASSIC,
     ));
-    game.add_slot(make_slot_pit!(0.0, 0.0));
+    let pit = game.add_slot(make_slot_pit!(0.0, 0.0));
 
-    game.tick();
+    game.tick().unwrap();
 
     // Both carriers should target the pit
     let carriers = game.get_carriers();
-    assert_eq!(carriers[0].get_reserved_target().unwrap(), 2);
-    assert_eq!(carriers[1].get_reserved_target().unwrap(), 2);
+    assert_eq!(carriers[c1].get_reserved_target().unwrap(), pit);
+    assert_eq!(carriers[c2].get_reserved_target().unwrap(), pit);
 }
 
 #[test]
@@ -105,7 +105,7 @@ fn issue19_try_to_crash_lots_of_carriers() {
         if *p > MAX_PAYLOAD {
             *p = 0
         };
-    };
+    }
 
     let mut game = swarm_it::Swarm::new();
 
@@ -152,7 +152,7 @@ fn issue19_try_to_crash_lots_of_carriers() {
 
     // Execute 1000 tics, expect no panic
     for _ in 0..60 {
-        game.tick();
+        game.tick().unwrap();
     }
 }
 
@@ -163,7 +163,7 @@ fn issue26_high_speed_carrier_not_reaching_target() {
     let mut game = swarm_it::Swarm::new();
 
     let i = game.add_carrier(Carrier::new(50.0, 50.0));
-    let mut carrier = game.get_carriers_mut()[i];
+    let carrier = &mut game.get_carriers_mut()[i];
     carrier.set_acceleration(2.0);
     carrier.set_max_speed(50.0);
 
@@ -181,10 +181,1746 @@ fn issue26_high_speed_carrier_not_reaching_target() {
 
     // After 60 ticks expect that carrier has a cargo
     for _ in 0..60 {
-        game.tick();
+        game.tick().unwrap();
     }
 
     let carriers = game.get_carriers();
     let payload = carriers[i].get_payload();
     assert!(payload.is_some());
 }
+
+#[test]
+fn slot_emptied_while_carrier_is_on_its_way() {
+    let mut game = swarm_it::Swarm::new();
+
+    let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+    let source = game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+
+    game.tick().unwrap();
+    game.get_slots_mut()[source].set_payloads(None);
+
+    let mut result = Ok(swarm_it::TickOutcome::InProgress);
+    for _ in 0..100 {
+        result = game.tick();
+        if result.is_err() {
+            break;
+        }
+    }
+    assert_eq!(
+        result,
+        Err(SwarmError::PickupFromEmpty {
+            carrier,
+            slot: source
+        })
+    );
+}
+
+#[test]
+fn slot_removed_while_carrier_is_on_its_way() {
+    let mut game = swarm_it::Swarm::new();
+
+    let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    let source = game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+
+    game.tick().unwrap();
+    game.get_slots_mut().remove(source);
+
+    assert_eq!(
+        game.tick(),
+        Err(SwarmError::StaleSlot {
+            carrier,
+            slot: source
+        })
+    );
+}
+
+#[test]
+fn removed_source_slot_makes_carrier_idle() {
+    let mut game = swarm_it::Swarm::new();
+
+    let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+    let target = game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    let source = game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+
+    game.tick().unwrap();
+    assert_eq!(game.get_carriers()[carrier].get_target(), Some(source));
+    assert!(game.remove_slot(source).is_some());
+    assert!(game.remove_slot(source).is_none());
+
+    assert_eq!(game.get_carriers()[carrier].get_state(), State::IDLE);
+    assert_eq!(game.get_carriers()[carrier].get_reserved_target(), None);
+    assert!(!game.get_slots()[target].is_taken_care_of());
+
+    // There is nothing that could be done without the source
+    let mut stalled = false;
+    for _ in 0..10 {
+        let outcome = game.tick().unwrap();
+        assert!(!outcome.is_finished());
+        if outcome.is_stalled() {
+            stalled = true;
+            break;
+        }
+    }
+    assert!(stalled);
+}
+
+#[test]
+fn removed_target_slot_makes_carrier_look_for_another() {
+    let mut game = swarm_it::Swarm::new();
+
+    let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    let near = game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    let far = game.add_slot(Slot::new(
+        800.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+
+    while game.get_carriers()[carrier].get_payload().is_none() {
+        game.tick().unwrap();
+    }
+    game.tick().unwrap();
+    assert_eq!(game.get_carriers()[carrier].get_target(), Some(near));
+    game.remove_slot(near);
+
+    let mut finished = false;
+    for _ in 0..2000 {
+        if game.tick().unwrap().is_finished() {
+            finished = true;
+            break;
+        }
+    }
+    assert!(finished);
+    assert_eq!(
+        game.get_slots()[far].get_payloads()[0],
+        Some(Payload::new('A'))
+    );
+}
+
+#[test]
+fn removed_carrier_releases_its_job() {
+    let mut game = swarm_it::Swarm::new();
+
+    let first = game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_carrier(Carrier::new(500.0, 500.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    let target = game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(make_slot_spawner!(300.0, 300.0));
+
+    while game.get_carriers()[first].get_payload().is_none() {
+        game.tick().unwrap();
+    }
+
+    // Payload is gone together with the carrier, spawner must provide a new one
+    let removed = game.remove_carrier(first).unwrap();
+    assert_eq!(removed.get_payload(), Some(Payload::new('A')));
+    assert!(!game.get_slots()[target].is_taken_care_of());
+
+    let mut finished = false;
+    for _ in 0..2000 {
+        if game.tick().unwrap().is_finished() {
+            finished = true;
+            break;
+        }
+    }
+    assert!(finished);
+    assert_eq!(game.get_carriers().len(), 1);
+    assert_eq!(
+        game.get_slots()[target].get_payloads()[0],
+        Some(Payload::new('A'))
+    );
+}
+
+#[test]
+fn optimal_assignment_sends_closest_carrier() {
+    fn check<S: swarm_it::DispatchStrategy<char> + 'static>(strategy: S, expect_near: bool) {
+        let mut game = swarm_it::Swarm::with_strategy(strategy);
+
+        // First carrier is far away, the second one stands next to the payload
+        let far = game.add_carrier(Carrier::new(1000.0, 1000.0));
+        let near = game.add_carrier(Carrier::new(110.0, 100.0));
+        let source = game.add_slot(Slot::new(
+            100.0,
+            100.0,
+            Some(Payload::new('A')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        let target = game.add_slot(Slot::new(
+            200.0,
+            100.0,
+            None,
+            Some(Payload::new('A')),
+            SlotKind::CLASSIC,
+        ));
+        game.tick().unwrap();
+
+        let (busy, idle) = if expect_near {
+            (near, far)
+        } else {
+            (far, near)
+        };
+        assert_eq!(game.get_carriers()[busy].get_target(), Some(source));
+        assert_eq!(
+            game.get_carriers()[busy].get_reserved_target(),
+            Some(target)
+        );
+        assert_eq!(game.get_carriers()[idle].get_target(), None);
+    }
+
+    check(swarm_it::GreedyStrategy, false);
+    check(swarm_it::OptimalStrategy, true);
+}
+
+#[test]
+fn optimal_assignment_picks_closest_task() {
+    fn check<S: swarm_it::DispatchStrategy<char> + 'static>(strategy: S, expect_near: bool) {
+        let mut game = swarm_it::Swarm::with_strategy(strategy);
+
+        // The only carrier stands next to the second payload
+        let carrier = game.add_carrier(Carrier::new(1000.0, 100.0));
+        let far = game.add_slot(Slot::new(
+            100.0,
+            100.0,
+            Some(Payload::new('A')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            200.0,
+            100.0,
+            None,
+            Some(Payload::new('A')),
+            SlotKind::CLASSIC,
+        ));
+        let near = game.add_slot(Slot::new(
+            900.0,
+            100.0,
+            Some(Payload::new('B')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            800.0,
+            100.0,
+            None,
+            Some(Payload::new('B')),
+            SlotKind::CLASSIC,
+        ));
+        game.tick().unwrap();
+
+        let expected = if expect_near { near } else { far };
+        assert_eq!(game.get_carriers()[carrier].get_target(), Some(expected));
+    }
+
+    check(swarm_it::GreedyStrategy, false);
+    check(swarm_it::OptimalStrategy, true);
+}
+
+#[test]
+fn optimal_assignment_finishes_the_job() {
+    let mut game = swarm_it::Swarm::with_strategy(swarm_it::OptimalStrategy);
+
+    for i in 0..4 {
+        game.add_carrier(Carrier::new(f64::from(i) * 100.0, 0.0));
+    }
+    for i in 0..4 {
+        game.add_slot(Slot::new(
+            f64::from(i) * 100.0,
+            100.0,
+            Some(Payload::new('A')),
+            Some(Payload::new('B')),
+            SlotKind::CLASSIC,
+        ));
+    }
+    game.add_slot(make_slot_pit!(0.0, 300.0));
+    game.add_slot(make_slot_spawner!(300.0, 300.0));
+
+    let mut finished = false;
+    for _ in 0..2000 {
+        if game.tick().unwrap().is_finished() {
+            finished = true;
+            break;
+        }
+    }
+    assert!(finished);
+    game.get_slots().values().for_each(|slot| {
+        let [current, target] = slot.get_payloads();
+        assert_eq!(current, target);
+    });
+}
+
+#[test]
+fn custom_strategy_that_gives_tasks_to_last_carrier() {
+    struct LastCarrierFirst;
+
+    impl swarm_it::DispatchStrategy<char> for LastCarrierFirst {
+        fn assign(
+            &mut self,
+            context: &swarm_it::DispatchContext<char>,
+        ) -> Vec<swarm_it::Assignment<char>> {
+            let mut planner = context.planner();
+            context
+                .idle_carriers()
+                .into_iter()
+                .rev()
+                .map_while(|carrier| {
+                    planner
+                        .next_task()
+                        .map(|task| swarm_it::Assignment { carrier, task })
+                })
+                .collect()
+        }
+    }
+
+    let mut game = swarm_it::Swarm::with_strategy(LastCarrierFirst);
+    let first = game.add_carrier(Carrier::new(0.0, 0.0));
+    let last = game.add_carrier(Carrier::new(0.0, 0.0));
+    let source = game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('X')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        None,
+        Some(Payload::new('X')),
+        SlotKind::CLASSIC,
+    ));
+
+    game.tick().unwrap();
+
+    assert_eq!(game.get_carriers()[first].get_state(), State::IDLE);
+    assert_eq!(game.get_carriers()[last].get_target(), Some(source));
+}
+
+#[test]
+fn invalid_assignment_is_reported() {
+    struct Broken;
+
+    impl swarm_it::DispatchStrategy<char> for Broken {
+        fn assign(
+            &mut self,
+            context: &swarm_it::DispatchContext<char>,
+        ) -> Vec<swarm_it::Assignment<char>> {
+            // Slot without payload cannot be picked up from
+            let (empty, _) = context
+                .slots()
+                .iter()
+                .find(|(_, slot)| slot.get_payloads()[0].is_none())
+                .unwrap();
+            vec![swarm_it::Assignment {
+                carrier: context.idle_carriers()[0],
+                task: swarm_it::Task::Transfer {
+                    slot: empty,
+                    target: None,
+                },
+            }]
+        }
+    }
+
+    let mut game = swarm_it::Swarm::with_strategy(Broken);
+    let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('X')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        None,
+        Some(Payload::new('X')),
+        SlotKind::CLASSIC,
+    ));
+
+    assert_eq!(game.tick(), Err(SwarmError::InvalidAssignment { carrier }));
+}
+
+#[test]
+fn events_of_simple_transfer() {
+    let mut game = swarm_it::Swarm::new();
+
+    let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+    let source = game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    let target = game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+
+    let mut events = Vec::new();
+    loop {
+        let finished = game.tick().unwrap().is_finished();
+        events.extend(game.drain_events());
+        if finished {
+            break;
+        }
+    }
+
+    let transfers: Vec<_> = events
+        .iter()
+        .filter(|event| !matches!(event, Event::StateChanged { .. }))
+        .cloned()
+        .collect();
+    assert_eq!(
+        transfers,
+        vec![
+            Event::PickedUp {
+                carrier,
+                slot: source,
+                cargo: 'A'
+            },
+            Event::PutDown {
+                carrier,
+                slot: target,
+                cargo: 'A'
+            }
+        ]
+    );
+
+    let states: Vec<_> = events
+        .iter()
+        .filter_map(|event| match *event {
+            Event::StateChanged { from, to, .. } => Some((from, to)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        states,
+        vec![
+            (State::IDLE, State::TARGETING(source)),
+            (State::TARGETING(source), State::MOVING(source)),
+            (State::MOVING(source), State::PICKINGUP(source)),
+            (State::PICKINGUP(source), State::LOOKINGFORTARGET),
+            (State::LOOKINGFORTARGET, State::TARGETING(target)),
+            (State::TARGETING(target), State::MOVING(target)),
+            (State::MOVING(target), State::PUTTINGDOWN(target)),
+            (State::PUTTINGDOWN(target), State::IDLE),
+        ]
+    );
+}
+
+#[test]
+fn events_of_pit_and_spawner() {
+    let mut game = swarm_it::Swarm::new();
+
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    let slot = game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        Some(Payload::new('B')),
+        SlotKind::CLASSIC,
+    ));
+    let pit = game.add_slot(make_slot_pit!(300.0, 100.0));
+    let spawner = game.add_slot(make_slot_spawner!(100.0, 300.0));
+
+    let mut events = Vec::new();
+    loop {
+        let finished = game.tick().unwrap().is_finished();
+        events.extend(game.drain_events());
+        if finished {
+            break;
+        }
+    }
+
+    assert!(events.iter().any(|event| matches!(
+        *event,
+        Event::DroppedIntoPit { slot: s, cargo: 'A', .. } if s == pit
+    )));
+    assert!(events.iter().any(|event| matches!(
+        *event,
+        Event::Spawned { slot: s, cargo: 'B', .. } if s == spawner
+    )));
+    assert!(events.iter().any(|event| matches!(
+        *event,
+        Event::PutDown { slot: s, cargo: 'B', .. } if s == slot
+    )));
+}
+
+#[test]
+fn result_does_not_depend_on_frame_rate() {
+    fn run(frame: f64) -> (f64, f64) {
+        let mut game = swarm_it::Swarm::new();
+        game.set_time_step(0.25);
+        let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+        game.add_slot(Slot::new(
+            500.0,
+            500.0,
+            Some(Payload::new('A')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            1000.0,
+            0.0,
+            None,
+            Some(Payload::new('A')),
+            SlotKind::CLASSIC,
+        ));
+
+        // 30 seconds in total
+        for _ in 0..(30.0 / frame) as usize {
+            game.tick_dt(frame).unwrap();
+        }
+        let pos = game.get_carriers()[carrier].get_position();
+        (pos.x, pos.y)
+    }
+
+    let slow = run(0.5);
+    let fast = run(0.125);
+    approx::assert_abs_diff_eq!(slow.0, fast.0);
+    approx::assert_abs_diff_eq!(slow.1, fast.1);
+    assert!(slow.0 > 0.0);
+}
+
+#[test]
+fn short_time_step_finishes_the_job() {
+    let mut game = swarm_it::Swarm::new();
+    game.set_time_step(0.1);
+
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    let target = game.add_slot(Slot::new(
+        300.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+
+    let mut finished = false;
+    for _ in 0..30000 {
+        if game.tick_dt(1.0 / 60.0).unwrap().is_finished() {
+            finished = true;
+            break;
+        }
+    }
+    assert!(finished);
+    assert_eq!(
+        game.get_slots()[target].get_payloads()[0],
+        Some(Payload::new('A'))
+    );
+}
+
+#[test]
+fn heterogeneous_fleet_finishes_the_job() {
+    let mut game = swarm_it::Swarm::new();
+
+    let mut forklift = Carrier::new(0.0, 0.0);
+    forklift.set_max_speed(9.0);
+    forklift.set_acceleration(1.0);
+    forklift.set_deceleration(2.0);
+    forklift.set_turn_rate(0.6);
+    forklift.set_turn_while_moving(true);
+    let mut tugger = Carrier::new(400.0, 0.0);
+    tugger.set_max_speed(3.0);
+    tugger.set_turn_rate(0.05);
+    game.add_carrier(forklift);
+    game.add_carrier(tugger);
+
+    for i in 0..4 {
+        game.add_slot(Slot::new(
+            f64::from(i) * 100.0,
+            100.0,
+            Some(Payload::new('A')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            f64::from(i) * 100.0,
+            300.0,
+            None,
+            Some(Payload::new('A')),
+            SlotKind::CLASSIC,
+        ));
+    }
+
+    let mut finished = false;
+    for _ in 0..5000 {
+        if game.tick().unwrap().is_finished() {
+            finished = true;
+            break;
+        }
+    }
+    assert!(finished);
+    game.get_slots().values().for_each(|slot| {
+        let [current, target] = slot.get_payloads();
+        assert_eq!(current, target);
+    });
+}
+
+#[test]
+fn same_seed_gives_identical_runs() {
+    // Position and angle of each carrier
+    type Carriers = Vec<(f64, f64, f64)>;
+
+    fn run(seed: u64) -> (Carriers, Vec<Event<char>>) {
+        let mut game = swarm_it::Swarm::with_seed(seed);
+        for i in 0..6 {
+            game.add_carrier(Carrier::new(f64::from(i) * 50.0, 0.0));
+        }
+        for (i, &(current, target)) in [
+            (Some('A'), Some('B')),
+            (Some('B'), Some('C')),
+            (Some('C'), None),
+            (None, Some('A')),
+            (Some('D'), Some('E')),
+            (None, Some('D')),
+        ]
+        .iter()
+        .enumerate()
+        {
+            game.add_slot(Slot::new(
+                i as f64 * 100.0,
+                200.0,
+                current.map(Payload::new),
+                target.map(Payload::new),
+                SlotKind::CLASSIC,
+            ));
+        }
+        game.add_slot(make_slot_pit!(0.0, 400.0));
+        game.add_slot(make_slot_spawner!(500.0, 400.0));
+
+        let mut events = Vec::new();
+        for _ in 0..1500 {
+            game.tick().unwrap();
+            events.extend(game.drain_events());
+        }
+        let carriers = game
+            .get_carriers()
+            .values()
+            .map(|c| (c.get_position().x, c.get_position().y, c.get_angle()))
+            .collect();
+        (carriers, events)
+    }
+
+    let (carriers, events) = run(42);
+    let (carriers_again, events_again) = run(42);
+    assert_eq!(carriers, carriers_again);
+    assert_eq!(events, events_again);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn restored_swarm_continues_where_it_left_off() {
+    let mut game = swarm_it::Swarm::with_seed(7);
+    for i in 0..3 {
+        game.add_carrier(Carrier::new(f64::from(i) * 100.0, 0.0));
+    }
+    for i in 0..4 {
+        game.add_slot(Slot::new(
+            f64::from(i) * 100.0,
+            100.0,
+            Some(Payload::new('A')),
+            Some(Payload::new('B')),
+            SlotKind::CLASSIC,
+        ));
+    }
+    game.add_slot(make_slot_pit!(0.0, 300.0));
+    game.add_slot(make_slot_spawner!(300.0, 300.0));
+
+    // Stop in the middle of the job, with carriers busy
+    for _ in 0..150 {
+        game.tick().unwrap();
+    }
+    let snapshot = serde_json::to_string(&game).unwrap();
+    let mut restored: swarm_it::Swarm<char> = serde_json::from_str(&snapshot).unwrap();
+
+    for _ in 0..1500 {
+        let outcome = game.tick().unwrap();
+        assert_eq!(restored.tick().unwrap(), outcome);
+    }
+    assert_eq!(
+        restored.drain_events().collect::<Vec<_>>(),
+        game.drain_events().collect::<Vec<_>>()
+    );
+    for ((id, carrier), (restored_id, restored_carrier)) in game
+        .get_carriers()
+        .iter()
+        .zip(restored.get_carriers().iter())
+    {
+        assert_eq!(id, restored_id);
+        assert_eq!(carrier.get_position().x, restored_carrier.get_position().x);
+        assert_eq!(carrier.get_position().y, restored_carrier.get_position().y);
+        assert_eq!(carrier.get_state(), restored_carrier.get_state());
+    }
+    restored.get_slots().values().for_each(|slot| {
+        let [current, target] = slot.get_payloads();
+        assert_eq!(current, target);
+    });
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn restored_swarm_makes_the_same_decisions() {
+    let mut game = swarm_it::Swarm::with_seed(3);
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    // Plenty of cargo kinds to be dropped and spawned, chosen by the order of the cargo balance
+    for (i, (current, target)) in ('a'..='z').zip('A'..='Z').enumerate() {
+        game.add_slot(Slot::new(
+            f64::from(i as u32) * 50.0,
+            100.0,
+            Some(Payload::new(current)),
+            Some(Payload::new(target)),
+            SlotKind::CLASSIC,
+        ));
+    }
+    game.add_slot(make_slot_pit!(0.0, 300.0));
+    game.add_slot(make_slot_spawner!(600.0, 300.0));
+    for _ in 0..30 {
+        game.tick().unwrap();
+    }
+    game.drain_events().for_each(drop);
+
+    let snapshot = serde_json::to_string(&game).unwrap();
+    let mut restored: swarm_it::Swarm<char> = serde_json::from_str(&snapshot).unwrap();
+    for _ in 0..500 {
+        game.tick().unwrap();
+        restored.tick().unwrap();
+    }
+    assert_eq!(
+        restored.drain_events().collect::<Vec<_>>(),
+        game.drain_events().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn carriers_with_radius_do_not_drive_through_each_other() {
+    fn run(radius: f64) -> (bool, f64) {
+        let mut game = swarm_it::Swarm::with_seed(3);
+        let mut first = Carrier::new(0.0, 0.0);
+        let mut second = Carrier::new(400.0, 5.0);
+        first.set_radius(radius);
+        second.set_radius(radius);
+        let first = game.add_carrier(first);
+        let second = game.add_carrier(second);
+
+        // Carriers are travelling in opposite directions along the same line
+        game.add_slot(Slot::new(
+            50.0,
+            0.0,
+            Some(Payload::new('A')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            350.0,
+            0.0,
+            None,
+            Some(Payload::new('A')),
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            350.0,
+            5.0,
+            Some(Payload::new('B')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            50.0,
+            5.0,
+            None,
+            Some(Payload::new('B')),
+            SlotKind::CLASSIC,
+        ));
+
+        let mut min_distance = f64::MAX;
+        for _ in 0..3000 {
+            let finished = game.tick().unwrap().is_finished();
+            let p1 = *game.get_carriers()[first].get_position();
+            let p2 = *game.get_carriers()[second].get_position();
+            min_distance = min_distance.min(((p1.x - p2.x).powi(2) + (p1.y - p2.y).powi(2)).sqrt());
+            if finished {
+                let done = game.get_slots().values().all(|slot| {
+                    let [current, target] = slot.get_payloads();
+                    current == target
+                });
+                return (done, min_distance);
+            }
+        }
+        (false, min_distance)
+    }
+
+    let (finished, min_distance) = run(0.0);
+    assert!(finished);
+    assert!(min_distance < 10.0);
+
+    let (finished, min_distance) = run(15.0);
+    assert!(finished);
+    assert!(min_distance > 20.0);
+}
+
+#[test]
+fn carrier_goes_around_the_wall() {
+    fn run(turn_while_moving: bool) {
+        let mut game = swarm_it::Swarm::with_seed(5);
+        let mut carrier = Carrier::new(50.0, 50.0);
+        carrier.set_turn_while_moving(turn_while_moving);
+        let carrier = game.add_carrier(carrier);
+
+        // Wall between the slots, with the passage at the bottom
+        let mut obstacles = ObstacleGrid::new(0.0, 0.0, 10.0, 30, 30);
+        obstacles.block_rect(140.0, 0.0, 159.0, 219.0);
+        game.set_obstacles(obstacles.clone());
+
+        game.add_slot(Slot::new(
+            50.0,
+            100.0,
+            Some(Payload::new('A')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(Slot::new(
+            250.0,
+            100.0,
+            None,
+            Some(Payload::new('A')),
+            SlotKind::CLASSIC,
+        ));
+        game.slot_data_changed();
+
+        let mut lowest = 0.0f64;
+        for _ in 0..3000 {
+            let finished = game.tick().unwrap().is_finished();
+            let pos = *game.get_carriers()[carrier].get_position();
+            assert!(!obstacles.is_blocked(pos.x, pos.y));
+            lowest = lowest.max(pos.y);
+            if finished {
+                let done = game.get_slots().values().all(|slot| {
+                    let [current, target] = slot.get_payloads();
+                    current == target
+                });
+                assert!(done);
+                assert!(lowest > 220.0);
+                return;
+            }
+        }
+        panic!("Carrier did not finish the job");
+    }
+
+    run(false);
+    run(true);
+}
+
+#[test]
+fn enclosed_slot_is_skipped_and_other_carriers_keep_working() {
+    let mut game = swarm_it::Swarm::with_seed(5);
+    game.add_carrier(Carrier::new(50.0, 50.0));
+    game.add_carrier(Carrier::new(50.0, 250.0));
+
+    // Target of 'A' is walled off on all sides
+    let mut obstacles = ObstacleGrid::new(0.0, 0.0, 10.0, 30, 30);
+    obstacles.block_rect(200.0, 50.0, 299.0, 59.0);
+    obstacles.block_rect(200.0, 140.0, 299.0, 149.0);
+    obstacles.block_rect(200.0, 60.0, 209.0, 139.0);
+    obstacles.block_rect(290.0, 60.0, 299.0, 139.0);
+    game.set_obstacles(obstacles);
+
+    game.add_slot(Slot::new(
+        50.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    let enclosed = game.add_slot(Slot::new(
+        250.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        50.0,
+        200.0,
+        Some(Payload::new('B')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    let other = game.add_slot(Slot::new(
+        150.0,
+        250.0,
+        None,
+        Some(Payload::new('B')),
+        SlotKind::CLASSIC,
+    ));
+
+    let mut unreachable = Vec::new();
+    for _ in 0..1000 {
+        game.tick().unwrap();
+        unreachable.extend(game.drain_events().filter_map(|event| match event {
+            Event::Unreachable { slot, .. } => Some(slot),
+            _ => None,
+        }));
+        for carrier in game.get_carriers().values() {
+            let position = carrier.get_position();
+            assert!(position.x < 200.0 || position.x > 300.0 || position.y > 150.0);
+        }
+    }
+    assert_eq!(unreachable, [enclosed]);
+    assert!(game.get_slots()[enclosed].is_unreachable());
+    assert!(game.get_slots()[other].is_satisfied());
+}
+
+#[test]
+fn slot_outside_of_the_obstacle_grid_is_reached() {
+    let mut game = swarm_it::Swarm::with_seed(5);
+    game.add_carrier(Carrier::new(50.0, 50.0));
+
+    // Grid covers the carrier, but not the slots
+    let mut obstacles = ObstacleGrid::new(0.0, 0.0, 10.0, 10, 10);
+    obstacles.block_rect(0.0, 80.0, 99.0, 99.0);
+    game.set_obstacles(obstacles);
+    game.add_slot(Slot::new(
+        200.0,
+        50.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        300.0,
+        50.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+
+    for _ in 0..1000 {
+        if game.tick().unwrap().is_finished() {
+            assert!(game
+                .drain_events()
+                .all(|event| !matches!(event, Event::Unreachable { .. })));
+            return;
+        }
+    }
+    panic!("Carrier did not finish the job");
+}
+
+#[test]
+fn carrier_with_capacity_batches_payloads() {
+    fn run(capacity: usize) -> (usize, u32) {
+        let mut game = swarm_it::Swarm::with_seed(8);
+        let mut carrier = Carrier::new(0.0, 0.0);
+        carrier.set_capacity(capacity);
+        let carrier = game.add_carrier(carrier);
+
+        for (i, &cargo) in ['A', 'B', 'C'].iter().enumerate() {
+            let y = 100.0 + 50.0 * i as f64;
+            game.add_slot(Slot::new(
+                0.0,
+                y,
+                Some(Payload::new(cargo)),
+                None,
+                SlotKind::CLASSIC,
+            ));
+            game.add_slot(Slot::new(
+                400.0,
+                y,
+                None,
+                Some(Payload::new(cargo)),
+                SlotKind::CLASSIC,
+            ));
+        }
+        // Excessive payload that goes to the pit
+        game.add_slot(Slot::new(
+            0.0,
+            250.0,
+            Some(Payload::new('D')),
+            None,
+            SlotKind::CLASSIC,
+        ));
+        game.add_slot(make_slot_pit!(400.0, 300.0));
+
+        let mut most_carried = 0;
+        for ticks in 0..10000 {
+            let finished = game.tick().unwrap().is_finished();
+            most_carried = most_carried.max(game.get_carriers()[carrier].get_payloads().len());
+            if finished {
+                assert!(game.get_slots().values().all(|slot| {
+                    let [current, target] = slot.get_payloads();
+                    current == target
+                }));
+                return (most_carried, ticks);
+            }
+        }
+        panic!("Carrier did not finish the job");
+    }
+
+    let (most_carried, single_ticks) = run(1);
+    assert_eq!(most_carried, 1);
+
+    let (most_carried, batch_ticks) = run(4);
+    assert_eq!(most_carried, 4);
+    assert!(batch_ticks < single_ticks);
+}
+
+#[test]
+fn bins_are_sorted_unit_by_unit() {
+    let mut game = swarm_it::Swarm::with_seed(13);
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_carrier(Carrier::new(300.0, 0.0));
+
+    // First shelf is full, it must give away both 'A's before it can take the 'B'
+    game.add_slot(Slot::new_bin(
+        0.0,
+        100.0,
+        3,
+        vec!['A', 'A', 'B'],
+        vec!['B', 'B'],
+    ));
+    game.add_slot(Slot::new_bin(300.0, 100.0, 3, vec!['C'], vec!['A', 'A']));
+    game.add_slot(Slot::new(
+        150.0,
+        200.0,
+        Some(Payload::new('B')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(make_slot_pit!(150.0, 0.0));
+
+    let mut picked_up = 0;
+    for _ in 0..10000 {
+        let finished = game.tick().unwrap().is_finished();
+        picked_up += game
+            .drain_events()
+            .filter(|event| matches!(event, Event::PickedUp { .. }))
+            .count();
+        if finished {
+            assert!(game.get_slots().values().all(|slot| slot.is_satisfied()));
+            assert_eq!(picked_up, 4);
+            return;
+        }
+    }
+    panic!("Carriers did not finish the job");
+}
+
+#[cfg(feature = "layout")]
+#[test]
+fn layout_file_is_loaded_and_saved() {
+    let path = std::env::temp_dir().join("swarm_it_layout_file_is_loaded_and_saved.json");
+    std::fs::write(
+        &path,
+        r#"{
+            "slots": [
+                { "x": 0.0, "y": 100.0, "current": ["A"], "target": ["B"], "priority": 2 },
+                { "x": 200.0, "y": 100.0, "capacity": 2, "current": ["B", "C"], "target": ["A"], "deadline": 500.0 },
+                { "x": 100.0, "y": 300.0, "kind": "PIT" }
+            ],
+            "carriers": [
+                { "x": 0.0, "y": 0.0, "max_speed": 6.0, "deceleration": 1.5 },
+                { "x": 200.0, "y": 0.0, "capacity": 2, "turn_while_moving": true }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let mut game = swarm_it::Swarm::<char>::from_layout_file(&path).unwrap();
+    assert_eq!(
+        game.get_carriers().values().next().unwrap().get_max_speed(),
+        6.0
+    );
+    let slots: Vec<_> = game.get_slots().values().collect();
+    assert_eq!(slots[0].get_priority(), 2);
+    assert_eq!(slots[1].get_deadline(), Some(500.0));
+    game.save_layout(&path).unwrap();
+    let loaded = swarm_it::Swarm::<char>::from_layout_file(&path).unwrap();
+    assert_eq!(loaded.to_layout(), game.to_layout());
+    assert_eq!(
+        loaded
+            .get_carriers()
+            .values()
+            .next()
+            .unwrap()
+            .get_deceleration(),
+        1.5
+    );
+
+    for _ in 0..5000 {
+        if game.tick().unwrap().is_finished() {
+            assert!(game.get_slots().values().all(|slot| slot.is_satisfied()));
+            return;
+        }
+    }
+    panic!("Carriers did not finish the job");
+}
+
+#[cfg(feature = "layout")]
+#[test]
+fn invalid_layout_is_reported() {
+    let mut game = swarm_it::Swarm::<char>::new();
+    let layout = serde_json::from_str(
+        r#"{ "slots": [
+            { "x": 0.0, "y": 0.0, "current": ["A"] },
+            { "x": 0.0, "y": 0.0, "kind": "PIT", "current": ["A"] }
+        ] }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        game.add_layout(&layout),
+        Err(swarm_it::LayoutError::InvalidSlot { index: 1 })
+    ));
+    assert!(game.get_slots().is_empty());
+
+    let layout = serde_json::from_str(
+        r#"{ "carriers": [
+            { "x": 0.0, "y": 0.0, "max_speed": 6.0 },
+            { "x": 0.0, "y": 0.0, "acceleration": 0.0 }
+        ] }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        game.add_layout(&layout),
+        Err(swarm_it::LayoutError::InvalidCarrier { index: 1 })
+    ));
+    assert!(game.get_carriers().is_empty());
+
+    assert!(matches!(
+        swarm_it::Swarm::<char>::from_layout_file("no/such/layout.json"),
+        Err(swarm_it::LayoutError::Io(_))
+    ));
+}
+
+#[test]
+fn text_is_morphed_into_another() {
+    let mut game = swarm_it::Swarm::<char>::with_seed(18);
+    let mut text = swarm_it::TextLayout::new(30.0);
+    text.set_origin(0.0, 100.0);
+    game.add_text_layout(&text, "SWARM  #\nIT     *", "WARMS  #\n  IT   *")
+        .unwrap();
+    for i in 0..3 {
+        game.add_carrier(Carrier::new(f64::from(i) * 100.0, 0.0));
+    }
+
+    for _ in 0..10000 {
+        if game.tick().unwrap().is_finished() {
+            let mut morphed = text.slots("", "WARMS\n  IT").unwrap().into_iter();
+            for slot in game.get_slots().values() {
+                if !slot.is_pit() && !slot.is_spawner() {
+                    let [current, target] = slot.get_payloads();
+                    assert_eq!(current, target);
+                }
+            }
+            assert!(
+                morphed.all(|expected| game.get_slots().values().any(|slot| {
+                    slot.get_position().x == expected.get_position().x
+                        && slot.get_position().y == expected.get_position().y
+                        && slot.get_payloads()[0] == expected.get_payloads()[1]
+                }))
+            );
+            return;
+        }
+    }
+    panic!("Carriers did not finish the job");
+}
+
+#[cfg(feature = "layout")]
+#[test]
+fn simulator_reports_statistics() {
+    let path = std::env::temp_dir().join("swarm_it_simulator_reports_statistics.json");
+    std::fs::write(
+        &path,
+        r#"{
+            "slots": [
+                { "x": 0.0, "y": 100.0, "current": ["A"], "target": ["B"] },
+                { "x": 200.0, "y": 100.0, "current": ["B"], "target": ["A"] },
+                { "x": 400.0, "y": 100.0, "current": ["C"] },
+                { "x": 100.0, "y": 300.0, "kind": "PIT" }
+            ],
+            "carriers": [{ "x": 0.0, "y": 0.0 }, { "x": 400.0, "y": 0.0 }]
+        }"#,
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_swarm-sim"))
+        .arg(&path)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["finished"], true);
+    assert_eq!(report["pit_drops"], 1);
+    assert_eq!(report["put_downs"], 2);
+    assert_eq!(report["carrier_distances"].as_array().unwrap().len(), 2);
+    assert!(report["total_distance"].as_f64().unwrap() > 0.0);
+
+    // Tick limit reached before the job is finished
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_swarm-sim"))
+        .arg(&path)
+        .args(["--max-ticks", "10"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    // There is no pit for the surplus cargo
+    std::fs::write(
+        &path,
+        r#"{
+            "slots": [{ "x": 0.0, "y": 100.0, "current": ["A"] }],
+            "carriers": [{ "x": 0.0, "y": 0.0 }]
+        }"#,
+    )
+    .unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_swarm-sim"))
+        .arg(&path)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["finished"], false);
+    assert!(report["ticks"].as_u64().unwrap() < 10);
+    assert!(report["stalled"].as_str().unwrap().contains("surplus: 1xA"));
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn small_swarm_is_rendered_in_the_same_colors_in_every_run() {
+    use swarm_it::Renderer;
+
+    let mut game = swarm_it::Swarm::with_seed(3);
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        0.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        200.0,
+        0.0,
+        Some(Payload::new('B')),
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(make_slot_pit!(300.0, 0.0));
+    let mut renderer = Renderer::new(400, 200);
+    renderer.fit(&game);
+
+    let svg = renderer.render_svg(&game);
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.ends_with("</svg>\n"));
+    assert!(svg.contains(">A</text>"));
+    assert!(svg.contains(">B</text>"));
+    // Colors of the cargo don't depend on the build or on the run
+    assert!(svg.contains("fill=\"#28dc64\""));
+    assert!(svg.contains("fill=\"#2842dc\""));
+
+    game.tick().unwrap();
+    let next = renderer.render_svg(&game);
+    assert_ne!(next, svg);
+    assert_eq!(renderer.render_svg(&game), next);
+}
+
+#[cfg(feature = "replay")]
+#[test]
+fn recorded_run_is_replayed_tick_for_tick() {
+    use swarm_it::{Recorder, Recording, ReplayError, Replayer};
+
+    let mut game = swarm_it::Swarm::with_seed(11);
+    for i in 0..3 {
+        game.add_carrier(Carrier::new(f64::from(i) * 100.0, 0.0));
+    }
+    let source = game.add_slot(Slot::new(
+        0.0,
+        100.0,
+        Some(Payload::new('A')),
+        Some(Payload::new('B')),
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(make_slot_pit!(0.0, 300.0));
+    game.add_slot(make_slot_spawner!(300.0, 300.0));
+
+    // Swarm is changed while the carriers are busy
+    let mut recorder = Recorder::new(game).unwrap();
+    for tick in 0..1200 {
+        match tick {
+            50 => {
+                recorder.add_carrier(Carrier::new(300.0, 0.0));
+            }
+            100 => {
+                recorder.add_slot(Slot::new(
+                    200.0,
+                    100.0,
+                    Some(Payload::new('C')),
+                    Some(Payload::new('A')),
+                    SlotKind::CLASSIC,
+                ));
+            }
+            300 => {
+                recorder.update_slot(source, |slot| {
+                    slot.set_target_payload(Some(Payload::new('C')))
+                });
+            }
+            400 => {
+                recorder.with_slots_mut(|slots| {
+                    slots
+                        .iter_mut()
+                        .for_each(|(_, slot)| slot.set_target_payload(None))
+                });
+                recorder.slot_data_changed();
+            }
+            _ => (),
+        }
+        recorder.tick().unwrap();
+    }
+    let expected: Vec<(f64, f64)> = recorder
+        .get_swarm()
+        .get_carriers()
+        .values()
+        .map(|c| (c.get_position().x, c.get_position().y))
+        .collect();
+
+    let mut file = Vec::new();
+    recorder.into_recording().write(&mut file).unwrap();
+    let recording = Recording::<char>::read(file.as_slice()).unwrap();
+    assert_eq!(recording.ticks(), 1200);
+
+    // Recording of another version is rejected by its header, the rest is not decoded
+    let mut other_version = file[..4].to_vec();
+    other_version[0] += 1;
+    match Recording::<char>::read(other_version.as_slice()) {
+        Err(ReplayError::Format(e)) => {
+            assert_eq!(e.to_string(), "unsupported recording version 2")
+        }
+        _ => panic!("recording of another version should be rejected"),
+    }
+
+    let mut replayer = Replayer::new(&recording).unwrap();
+    replayer.run(true).unwrap();
+    assert_eq!(replayer.get_tick(), 1200);
+    let replayed: Vec<(f64, f64)> = replayer
+        .get_swarm()
+        .get_carriers()
+        .values()
+        .map(|c| (c.get_position().x, c.get_position().y))
+        .collect();
+    assert_eq!(replayed, expected);
+
+    // Replaying with another strategy sends another carrier
+    let mut game = swarm_it::Swarm::new();
+    game.add_carrier(Carrier::new(1000.0, 1000.0));
+    game.add_carrier(Carrier::new(110.0, 100.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    let mut recorder = Recorder::new(game).unwrap();
+    recorder.set_strategy(swarm_it::OptimalStrategy);
+    for _ in 0..200 {
+        recorder.tick().unwrap();
+    }
+    let mut replayer = Replayer::new(&recorder.into_recording()).unwrap();
+    match replayer.run(true) {
+        Err(ReplayError::Diverged { tick }) => assert!(tick >= 1),
+        _ => panic!("replay with another strategy should diverge"),
+    }
+}
+
+#[test]
+fn stalled_swarm_is_reported() {
+    // There is no target for the surplus 'X' and nothing to fill the slot that waits for 'Y'
+    let mut game = swarm_it::Swarm::with_seed(5);
+    game.set_stall_limit_secs(300.0);
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('X')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        300.0,
+        100.0,
+        None,
+        Some(Payload::new('Y')),
+        SlotKind::CLASSIC,
+    ));
+
+    let mut stalled = Vec::new();
+    for _ in 0..1000 {
+        game.tick().unwrap();
+        stalled.extend(game.drain_events().filter_map(|event| match event {
+            Event::Stalled { seconds } => Some(seconds),
+            _ => None,
+        }));
+    }
+    assert_eq!(stalled, [300.0]);
+    let report = game.get_stall_report().unwrap();
+    approx::assert_abs_diff_eq!(report.get_seconds_without_progress(), 1000.0);
+    assert_eq!(report.get_surplus(), [('X', 1)]);
+    assert_eq!(report.get_shortage(), [('Y', 1)]);
+    assert!(report.get_cycling().is_empty());
+
+    // Spawner and pit make the job possible
+    game.add_slot(make_slot_spawner!(400.0, 100.0));
+    game.add_slot(make_slot_pit!(0.0, 100.0));
+    assert!(game.get_stall_report().is_none());
+    while !game.tick().unwrap().is_finished() {}
+    assert!(game.get_stall_report().is_none());
+}
+
+#[test]
+fn stall_limit_does_not_depend_on_time_step() {
+    // Single long trip takes many short steps
+    let mut game = swarm_it::Swarm::with_seed(5);
+    game.set_time_step(0.01);
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        0.0,
+        0.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        300.0,
+        0.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    let mut finished = false;
+    for _ in 0..500 {
+        let outcome = game.tick().unwrap();
+        assert!(!outcome.is_stalled());
+        if outcome.is_finished() {
+            finished = true;
+            break;
+        }
+    }
+    assert!(finished);
+    assert!(game.get_time() > 10.0);
+    assert!(!game
+        .drain_events()
+        .any(|event| matches!(event, Event::Stalled { .. })));
+
+    // Nothing to fill the slot with, the limit is reached after the same number of ticks
+    let mut game = swarm_it::Swarm::with_seed(5);
+    game.set_time_step(0.1);
+    game.set_stall_limit_secs(30.0);
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        300.0,
+        100.0,
+        None,
+        Some(Payload::new('Y')),
+        SlotKind::CLASSIC,
+    ));
+    for tick in 1..=40 {
+        game.tick().unwrap();
+        let stalled = game.drain_events().any(|event| match event {
+            Event::Stalled { seconds } => approx::abs_diff_eq!(seconds, 30.0, epsilon = 1e-9),
+            _ => false,
+        });
+        assert_eq!(stalled, tick == 30);
+    }
+    approx::assert_abs_diff_eq!(
+        game.get_stall_report()
+            .unwrap()
+            .get_seconds_without_progress(),
+        40.0,
+        epsilon = 1e-9
+    );
+}
+
+#[test]
+fn carrier_without_target_is_reported_as_cycling() {
+    let mut game = swarm_it::Swarm::with_seed(5);
+    game.set_stall_limit_secs(300.0);
+    let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        Some(Payload::new('B')),
+        SlotKind::CLASSIC,
+    ));
+    let target = game.add_slot(Slot::new(
+        300.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+
+    // Target is removed once the cargo is picked up, there is nowhere to put it
+    while game.get_carriers()[carrier].get_payload().is_none() {
+        game.tick().unwrap();
+    }
+    game.remove_slot(target);
+    for _ in 0..300 {
+        game.tick().unwrap();
+    }
+    assert!(game
+        .drain_events()
+        .any(|event| event == Event::Stalled { seconds: 300.0 }));
+    let report = game.get_stall_report().unwrap();
+    assert_eq!(report.get_cycling(), [carrier]);
+    assert_eq!(report.get_surplus(), [('A', 1)]);
+}
+
+#[test]
+fn progress_is_reported_until_completed() {
+    let mut game = swarm_it::Swarm::with_seed(3);
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        Some(Payload::new('B')),
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        Some(Payload::new('B')),
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    game.add_slot(Slot::new(
+        300.0,
+        100.0,
+        Some(Payload::new('C')),
+        Some(Payload::new('C')),
+        SlotKind::CLASSIC,
+    ));
+    // Free slot for swapping the payloads
+    game.add_slot(Slot::new(400.0, 100.0, None, None, SlotKind::CLASSIC));
+
+    let progress = game.progress();
+    assert_eq!(progress.get_matched_slots(), 2);
+    assert_eq!(progress.get_mismatched_slots(), 2);
+    assert!(progress.get_cargo_balance().is_empty());
+    assert_eq!(*progress.get_status(), swarm_it::JobStatus::Running);
+
+    let mut outcome = game.tick().unwrap();
+    while outcome == swarm_it::TickOutcome::InProgress {
+        assert_eq!(*game.progress().get_status(), swarm_it::JobStatus::Running);
+        outcome = game.tick().unwrap();
+    }
+    assert!(outcome.is_finished());
+    let progress = game.progress();
+    assert_eq!(progress.get_matched_slots(), 4);
+    assert_eq!(progress.get_mismatched_slots(), 0);
+    assert_eq!(*progress.get_status(), swarm_it::JobStatus::Completed);
+
+    // New target makes the job unfinished again, but there is no 'D' anywhere
+    let last = game.get_slots().ids().nth(2).unwrap();
+    game.update_slot(last, |slot| {
+        slot.set_target_payload(Some(Payload::new('D')))
+    });
+    assert!(game.tick().unwrap().is_stalled());
+    let progress = game.progress();
+    assert_eq!(progress.get_mismatched_slots(), 1);
+    assert_eq!(progress.get_cargo_balance(), [('C', 1), ('D', -1)]);
+    match progress.get_status() {
+        swarm_it::JobStatus::Stalled { reason } => {
+            assert_eq!(reason.get_shortage(), [('D', 1)]);
+            assert!(reason.get_cycling().is_empty());
+        }
+        status => panic!("unexpected status {:?}", status),
+    }
+}
+
+#[test]
+fn urgent_slots_are_filled_first_and_missed_deadlines_are_reported() {
+    let mut game = swarm_it::Swarm::with_seed(9);
+    game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new_bin(0.0, 100.0, 2, vec!['A', 'A'], vec![]));
+    let near = game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    let mut urgent = Slot::new(
+        500.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    );
+    urgent.set_priority(1);
+    let urgent = game.add_slot(urgent);
+    game.update_slot(near, |slot| slot.set_deadline(Some(30.0)));
+
+    let mut events = Vec::new();
+    loop {
+        let finished = game.tick().unwrap().is_finished();
+        events.extend(game.drain_events());
+        if finished {
+            break;
+        }
+    }
+    let deliveries: Vec<_> = events
+        .iter()
+        .filter_map(|event| match *event {
+            Event::PutDown { slot, .. } => Some(slot),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(deliveries, [urgent, near]);
+
+    // The near slot waited for the urgent one and it was too late
+    let missed: Vec<_> = events
+        .iter()
+        .filter(|event| matches!(event, Event::DeadlineMissed { .. }))
+        .collect();
+    assert_eq!(missed, [&Event::DeadlineMissed { slot: near }]);
+    assert!(game.get_time() > 30.0);
+}
+
+#[test]
+fn optimal_assignment_serves_urgent_slots_first() {
+    let mut game = swarm_it::Swarm::with_strategy(swarm_it::OptimalStrategy);
+    let carrier = game.add_carrier(Carrier::new(0.0, 0.0));
+    game.add_slot(Slot::new(
+        100.0,
+        100.0,
+        Some(Payload::new('A')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    let near = game.add_slot(Slot::new(
+        200.0,
+        100.0,
+        None,
+        Some(Payload::new('A')),
+        SlotKind::CLASSIC,
+    ));
+    let far = game.add_slot(Slot::new(
+        1000.0,
+        100.0,
+        Some(Payload::new('B')),
+        None,
+        SlotKind::CLASSIC,
+    ));
+    let mut urgent = Slot::new(
+        1100.0,
+        100.0,
+        None,
+        Some(Payload::new('B')),
+        SlotKind::CLASSIC,
+    );
+    urgent.set_priority(1);
+    let urgent = game.add_slot(urgent);
+
+    // The closer task waits, although it would take less distance to travel
+    game.tick().unwrap();
+    assert_eq!(game.get_carriers()[carrier].get_target(), Some(far));
+
+    let mut deliveries = Vec::new();
+    loop {
+        let finished = game.tick().unwrap().is_finished();
+        deliveries.extend(game.drain_events().filter_map(|event| match event {
+            Event::PutDown { slot, .. } => Some(slot),
+            _ => None,
+        }));
+        if finished {
+            break;
+        }
+    }
+    assert_eq!(deliveries, [urgent, near]);
+}